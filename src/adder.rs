@@ -0,0 +1,149 @@
+use crate::address::{MpdAddress, MpdClient};
+use crate::app::AppResult;
+use crate::connection::{describe_error, is_connection_error};
+use crate::library;
+use mpd::{Query, Song, Term};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// Where the songs of a job are added
+#[derive(Debug, Clone, PartialEq)]
+pub enum Destination {
+    Queue,
+    Playlist(String),
+}
+
+/// What a job adds, each one is a single request to MPD where it can be
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    /// A song, by its file
+    File(String),
+    /// A directory of the database, subdirectories included
+    Directory(String),
+    /// The songs matching every tag, e.g. an album
+    Tags(Vec<(String, String)>),
+}
+
+impl Source {
+    pub fn from_songs(songs: &[Song]) -> Vec<Source> {
+        songs
+            .iter()
+            .map(|song| Source::File(song.file.clone()))
+            .collect()
+    }
+}
+
+/// What the worker reports for each source it was given
+#[derive(Debug)]
+enum Progress {
+    Added,
+    Failed(String),
+}
+
+/// Adds songs from a thread of its own, with its own connection, so that
+/// adding a big directory tree does not freeze the interface
+#[derive(Debug)]
+pub struct Adder {
+    jobs: Sender<(Destination, Vec<Source>)>,
+    progress: Receiver<Progress>,
+    pub done: usize,  // Sources added (or refused) since the adder was last idle
+    pub total: usize, // Sources given to the adder since it was last idle
+}
+
+impl Adder {
+    pub fn new(address: MpdAddress) -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<(Destination, Vec<Source>)>();
+        let (progress_sender, progress) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut client: Option<MpdClient> = None;
+            for (destination, sources) in job_receiver {
+                for source in sources {
+                    let result = match client.as_mut() {
+                        Some(client) => add(client, &destination, &source),
+                        None => address.connect().and_then(|mut new_client| {
+                            let result = add(&mut new_client, &destination, &source);
+                            client = Some(new_client);
+                            result
+                        }),
+                    };
+                    let report = match result {
+                        Ok(()) => Progress::Added,
+                        Err(err) => {
+                            if is_connection_error(err.as_ref()) {
+                                client = None;
+                            }
+                            Progress::Failed(describe_error(err.as_ref()))
+                        }
+                    };
+                    if progress_sender.send(report).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Self {
+            jobs,
+            progress,
+            done: 0,
+            total: 0,
+        }
+    }
+
+    /// Queue up sources to be added after the ones already waiting
+    pub fn add(&mut self, destination: Destination, sources: Vec<Source>) {
+        if sources.is_empty() {
+            return;
+        }
+        self.total += sources.len();
+        // The worker only stops when the adder is dropped
+        let _ = self.jobs.send((destination, sources));
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.done < self.total
+    }
+
+    /// Count the sources handled since the last call, returning the last error
+    pub fn poll(&mut self) -> Option<String> {
+        let mut error = None;
+        while let Ok(progress) = self.progress.try_recv() {
+            self.done += 1;
+            if let Progress::Failed(message) = progress {
+                error = Some(message);
+            }
+        }
+        if !self.is_busy() {
+            self.done = 0;
+            self.total = 0;
+        }
+        error
+    }
+}
+
+fn add(client: &mut MpdClient, destination: &Destination, source: &Source) -> AppResult<()> {
+    let song = |file: &str| Song {
+        file: file.to_string(),
+        ..Default::default()
+    };
+    match (destination, source) {
+        (Destination::Queue, Source::File(file)) => client.push(song(file)).map(|_| ()),
+        (Destination::Queue, Source::Directory(directory)) => {
+            let mut query = Query::new();
+            query.and(Term::Base, directory.as_str());
+            client.findadd(&query)
+        }
+        (Destination::Queue, Source::Tags(tags)) => client.findadd(&library::tag_query(tags)),
+        // playlistadd takes whole directories too
+        (Destination::Playlist(name), Source::File(file) | Source::Directory(file)) => {
+            client.pl_push(name, song(file))
+        }
+        // The mpd crate has no searchaddpl, the songs go one by one
+        (Destination::Playlist(name), Source::Tags(tags)) => {
+            let songs = client.find(&library::tag_query(tags), None)?;
+            songs.iter().try_for_each(|song| client.pl_push(name, song))
+        }
+    }?;
+    Ok(())
+}
@@ -0,0 +1,362 @@
+use crate::app::AppResult;
+use mpd::error::Error;
+use mpd::reply::Reply;
+use mpd::Client;
+use std::env;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+/// Port MPD listens on when MPD_PORT is not set
+pub const DEFAULT_PORT: u16 = 6600;
+
+/// Host used when MPD_HOST is not set
+pub const DEFAULT_HOST: &str = "127.0.0.1";
+
+/// mpd Client over whichever kind of socket the server listens on
+pub type MpdClient = Client<MpdStream>;
+
+#[derive(Debug, Clone, PartialEq)]
+/// The socket the MPD server listens on
+pub enum Endpoint {
+    Tcp {
+        host: String,
+        port: u16,
+    },
+    /// Unix domain socket, `MPD_HOST=/run/mpd/socket`
+    Unix(PathBuf),
+    /// Linux abstract socket, `MPD_HOST=@mpd`
+    Abstract(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Where the MPD server lives and how to authenticate with it
+pub struct MpdAddress {
+    pub endpoint: Endpoint,
+    pub password: Option<String>,
+}
+
+impl MpdAddress {
+    /// Build the address from the given host and port, falling back to the
+    /// MPD_HOST and MPD_PORT environment variables for the missing ones
+    ///
+    /// When neither is set, the socket in `$XDG_RUNTIME_DIR/mpd` is preferred if
+    /// it exists, as that is where most desktop setups have MPD listening.
+    ///
+    /// A port given here also beats the `:port` MPD_HOST may carry, while the
+    /// one of a host given here still beats the port.
+    pub fn resolve(host: Option<&str>, port: Option<&str>) -> AppResult<Self> {
+        let explicit_port = port.filter(|_| host.is_none());
+        let host = host
+            .map(str::to_string)
+            .or_else(|| env::var("MPD_HOST").ok());
+        let port = port
+            .map(str::to_string)
+            .or_else(|| env::var("MPD_PORT").ok());
+        match (host, port) {
+            (Some(host), port) => {
+                let mut address = Self::parse(&host, port.as_deref())?;
+                if let (Endpoint::Tcp { port, .. }, Some(explicit)) =
+                    (&mut address.endpoint, explicit_port)
+                {
+                    *port = parse_port(explicit)?;
+                }
+                Ok(address)
+            }
+            (None, port) => match env::var_os("XDG_RUNTIME_DIR") {
+                Some(dir) if port.is_none() && Path::new(&dir).join("mpd/socket").exists() => {
+                    Ok(Self {
+                        endpoint: Endpoint::Unix(Path::new(&dir).join("mpd/socket")),
+                        password: None,
+                    })
+                }
+                _ => Self::parse(DEFAULT_HOST, port.as_deref()),
+            },
+        }
+    }
+
+    /// Parse a host the way mpc does: `[password@]host`
+    ///
+    /// A host starting with `/` is the path of a Unix socket, `@` signs in it
+    /// included, and one starting with `@` names an abstract socket. IPv6
+    /// literals may be written in brackets, and the host may carry its own
+    /// `:port`, which takes precedence over the given port.
+    pub fn parse(host: &str, port: Option<&str>) -> AppResult<Self> {
+        let (password, host) = match host.split_once('@') {
+            Some((password, host)) if !password.is_empty() && !password.starts_with('/') => {
+                (Some(password.to_string()), host)
+            }
+            _ => (None, host),
+        };
+
+        if host.starts_with('/') {
+            return Ok(Self {
+                endpoint: Endpoint::Unix(PathBuf::from(host)),
+                password,
+            });
+        }
+        if let Some(name) = host.strip_prefix('@') {
+            return Ok(Self {
+                endpoint: Endpoint::Abstract(name.to_string()),
+                password,
+            });
+        }
+
+        let (host, host_port) = if let Some(rest) = host.strip_prefix('[') {
+            let (host, rest) = rest
+                .split_once(']')
+                .ok_or_else(|| format!("unterminated IPv6 address in \"{}\"", host))?;
+            (host, rest.strip_prefix(':'))
+        } else {
+            match host.split_once(':') {
+                // more than one colon is a bare IPv6 literal
+                Some((host, port)) if !port.contains(':') => (host, Some(port)),
+                _ => (host, None),
+            }
+        };
+
+        let port = match host_port.or(port) {
+            Some(port) => parse_port(port)?,
+            None => DEFAULT_PORT,
+        };
+
+        let host = if host.is_empty() { DEFAULT_HOST } else { host };
+
+        Ok(Self {
+            endpoint: Endpoint::Tcp {
+                host: host.to_string(),
+                port,
+            },
+            password,
+        })
+    }
+
+    /// Connect to MPD, sending the password if there is one
+    pub fn connect(&self) -> AppResult<MpdClient> {
+        let mut client = Client::new(MpdStream::connect(&self.endpoint)?)?;
+        if let Some(password) = &self.password {
+            client.login(password)?;
+        }
+        Ok(client)
+    }
+
+    /// Send a single command over a connection of its own and return the
+    /// pairs of the reply in the order they came, for the replies the mpd
+    /// crate does not keep whole
+    pub fn request(&self, command: &str, argument: &str) -> AppResult<Vec<(String, String)>> {
+        let mut stream = BufReader::new(MpdStream::connect(&self.endpoint)?);
+        let mut greeting = String::new();
+        stream.read_line(&mut greeting)?;
+        if !greeting.starts_with("OK MPD ") {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not an MPD server").into());
+        }
+        if let Some(password) = &self.password {
+            send(&mut stream, "password", password)?;
+        }
+        send(&mut stream, command, argument)
+    }
+}
+
+/// Write the command and read its reply up to the final OK
+fn send(
+    stream: &mut BufReader<MpdStream>,
+    command: &str,
+    argument: &str,
+) -> AppResult<Vec<(String, String)>> {
+    let argument = argument.replace('\\', "\\\\").replace('"', "\\\"");
+    writeln!(stream.get_mut(), "{} \"{}\"", command, argument)?;
+
+    let mut pairs = Vec::new();
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line)? == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        match line.trim_end_matches('\n').parse::<Reply>() {
+            Ok(Reply::Ok) => return Ok(pairs),
+            Ok(Reply::Ack(err)) => return Err(Error::Server(err).into()),
+            Ok(Reply::Pair(key, value)) => pairs.push((key, value)),
+            Err(err) => return Err(Error::Parse(err).into()),
+        }
+    }
+}
+
+fn parse_port(port: &str) -> AppResult<u16> {
+    Ok(port
+        .parse()
+        .map_err(|_| format!("invalid MPD port \"{}\"", port))?)
+}
+
+impl fmt::Display for MpdAddress {
+    /// Formats the address without the password, so it is safe to show in the UI
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.endpoint {
+            Endpoint::Tcp { host, port } if host.contains(':') => write!(f, "[{}]:{}", host, port),
+            Endpoint::Tcp { host, port } => write!(f, "{}:{}", host, port),
+            Endpoint::Unix(path) => write!(f, "{}", path.display()),
+            Endpoint::Abstract(name) => write!(f, "@{}", name),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Socket connected to MPD
+pub enum MpdStream {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl MpdStream {
+    /// Open the socket described by the endpoint
+    pub fn connect(endpoint: &Endpoint) -> io::Result<Self> {
+        match endpoint {
+            Endpoint::Tcp { host, port } => {
+                Ok(MpdStream::Tcp(TcpStream::connect((host.as_str(), *port))?))
+            }
+            Endpoint::Unix(path) => Ok(MpdStream::Unix(UnixStream::connect(path)?)),
+            Endpoint::Abstract(name) => Self::connect_abstract(name),
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn connect_abstract(name: &str) -> io::Result<Self> {
+        #[cfg(target_os = "android")]
+        use std::os::android::net::SocketAddrExt;
+        #[cfg(target_os = "linux")]
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::SocketAddr;
+
+        let addr = SocketAddr::from_abstract_name(name)?;
+        Ok(MpdStream::Unix(UnixStream::connect_addr(&addr)?))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn connect_abstract(_name: &str) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "abstract sockets are only supported on Linux",
+        ))
+    }
+}
+
+impl Read for MpdStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            MpdStream::Tcp(stream) => stream.read(buf),
+            MpdStream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for MpdStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            MpdStream::Tcp(stream) => stream.write(buf),
+            MpdStream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            MpdStream::Tcp(stream) => stream.flush(),
+            MpdStream::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tcp(host: &str, port: u16) -> Endpoint {
+        Endpoint::Tcp {
+            host: host.to_string(),
+            port,
+        }
+    }
+
+    fn parse(host: &str, port: Option<&str>) -> MpdAddress {
+        MpdAddress::parse(host, port).unwrap()
+    }
+
+    #[test]
+    fn host_and_port() {
+        assert_eq!(parse("box", None).endpoint, tcp("box", DEFAULT_PORT));
+        assert_eq!(parse("box", Some("6700")).endpoint, tcp("box", 6700));
+        assert_eq!(parse("", None).endpoint, tcp(DEFAULT_HOST, DEFAULT_PORT));
+    }
+
+    #[test]
+    fn port_in_host_wins() {
+        assert_eq!(parse("box:6601", Some("6700")).endpoint, tcp("box", 6601));
+    }
+
+    #[test]
+    fn password() {
+        let address = parse("secret@box:6601", None);
+        assert_eq!(address.password.as_deref(), Some("secret"));
+        assert_eq!(address.endpoint, tcp("box", 6601));
+        assert_eq!(address.to_string(), "box:6601");
+    }
+
+    #[test]
+    fn ipv6() {
+        assert_eq!(parse("[::1]:6601", None).endpoint, tcp("::1", 6601));
+        assert_eq!(parse("[::1]", Some("6700")).endpoint, tcp("::1", 6700));
+        assert_eq!(
+            parse("fe80::1", None).endpoint,
+            tcp("fe80::1", DEFAULT_PORT)
+        );
+        assert_eq!(parse("pw@[::1]", None).password.as_deref(), Some("pw"));
+        assert_eq!(parse("[::1]:6601", None).to_string(), "[::1]:6601");
+        assert!(MpdAddress::parse("[::1", None).is_err());
+    }
+
+    #[test]
+    fn invalid_port() {
+        assert!(MpdAddress::parse("box:port", None).is_err());
+        assert!(MpdAddress::parse("box", Some("70000")).is_err());
+    }
+
+    #[test]
+    fn unix_socket() {
+        let address = parse("/run/mpd/socket", Some("6700"));
+        assert_eq!(address.endpoint, Endpoint::Unix("/run/mpd/socket".into()));
+        assert_eq!(address.password, None);
+
+        let address = parse("secret@/run/mpd/socket", None);
+        assert_eq!(address.endpoint, Endpoint::Unix("/run/mpd/socket".into()));
+        assert_eq!(address.password.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn unix_socket_with_at_sign() {
+        let address = parse("/run/user@1000/mpd/socket", None);
+        assert_eq!(
+            address.endpoint,
+            Endpoint::Unix("/run/user@1000/mpd/socket".into())
+        );
+        assert_eq!(address.password, None);
+
+        let address = parse("pw@/run/user@1000/mpd/socket", None);
+        assert_eq!(
+            address.endpoint,
+            Endpoint::Unix("/run/user@1000/mpd/socket".into())
+        );
+        assert_eq!(address.password.as_deref(), Some("pw"));
+    }
+
+    #[test]
+    fn abstract_socket() {
+        let address = parse("@mpd", None);
+        assert_eq!(address.endpoint, Endpoint::Abstract("mpd".to_string()));
+        assert_eq!(address.password, None);
+        assert_eq!(address.to_string(), "@mpd");
+
+        let address = parse("secret@@mpd", None);
+        assert_eq!(address.endpoint, Endpoint::Abstract("mpd".to_string()));
+        assert_eq!(address.password.as_deref(), Some("secret"));
+    }
+}
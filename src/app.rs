@@ -1,11 +1,24 @@
-use std::{path::Path, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    str::FromStr,
+    time::Duration,
+};
 
+use crate::adder::{Adder, Destination, Source};
+use crate::address::{MpdAddress, MpdClient};
 use crate::browser::FileBrowser;
-use crate::connection::Connection;
-use crate::list::ContentList;
+use crate::config::{AddMode, Config};
+use crate::connection::{describe_error, is_connection_error, Connection, ConnectionState};
+use crate::doctor::{self, DeadEntry, Relocator};
+use crate::event_handler::keymap::Key;
+use crate::library::{self, LibraryBrowser, LibraryLevel};
+use crate::list::{ContentList, ListFilter, Selection};
+use crate::playlist_file::{self, Resolver};
+use crate::search;
+use crate::searchable::{Matcher, SearchMode, Searchable};
 use crate::ui::InputMode;
-use crate::utils::FileExtension;
-use mpd::{Client, Song};
+use mpd::{Song, Subsystem};
 use ratatui::widgets::{ListState, TableState};
 
 // Application result type
@@ -14,37 +27,113 @@ pub type AppResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 /// Application
 #[derive(Debug)]
 pub struct App {
-    pub running: bool,                 // Check if app is running
-    pub conn: Connection,              // Connection
-    pub browser: FileBrowser,          // Directory browser
-    pub queue_list: ContentList<Song>, // Stores the current playing queue
-    pub pl_list: ContentList<String>,  // Stores list of playlists
-    pub selected_tab: SelectedTab,     // Used to switch between tabs
+    pub running: bool,                     // Check if app is running
+    pub config: Config,                    // Settings from the configuration file
+    pub conn: Connection,                  // Connection
+    pub browser: FileBrowser,              // Directory browser
+    pub library: LibraryBrowser,           // Library browser, by tags
+    pub search_results: ContentList<Song>, // Songs found by the last database search
+    pub queue_list: ContentList<Song>,     // Stores the current playing queue
+    pub pl_list: ContentList<String>,      // Stores list of playlists
+    pub selected_tab: SelectedTab,         // Used to switch between tabs
+    pub error_message: Option<String>, // Last error MPD returned, shown until the next key press
+    pub pending_keys: Vec<Key>,        // Start of a key sequence waiting for its next key
+    pub adder: Adder,                  // Adds directory trees to the queue or playlists
 
     // Search
-    pub inputmode: InputMode,     // Defines input mode, Normal or Search
-    pub search_input: String,     // Stores the userinput to be searched
-    pub search_cursor_pos: usize, // Stores the cursor position for searching
+    pub inputmode: InputMode,        // Defines input mode, Normal or Search
+    pub search_input: String,        // Stores the userinput to be searched
+    pub search_cursor_pos: usize,    // Stores the cursor position for searching
+    pub last_search: String,         // Last query searched, for jumping between matches
+    pub search_mode: SearchMode,     // How the search compares the query with the rows
+    pub search_case_sensitive: bool, // Whether the search tells upper and lower case apart
+
+    pub db_search_input: String, // Stores the query of the database search
+    pub db_search_cursor_pos: usize, // Stores the cursor position of the database search
+    pub db_search_query: String, // Query of the results shown in the search tab
 
     pub pl_newname_input: String, // Stores the new name of the playlist
     pub pl_cursor_pos: usize,     // Stores the cursor position for renaming playlist
+    pub pl_name_prompt: PlaylistPrompt, // What the name typed in the playlist prompt is for
+    pub confirmation: Option<Confirmation>, // Action waiting for the user to confirm it
+
+    // Filters, hiding the rows which do not match
+    pub filter_input: String, // Stores the query of the filter being typed
+    pub filter_cursor_pos: usize, // Stores the cursor position of the filter
+    pub queue_filter: Option<ListFilter>, // Filter of the queue
+    pub browser_filter: Option<ListFilter>, // Filter of the directory browser
+    pub pl_filter: Option<ListFilter>, // Filter of the playlists
+
+    pub selection: Selection, // Rows of the current tab marked for the bulk actions
 
     pub pl_new_pl_input: String, // Stores the name of new playlist to be created
     pub pl_new_pl_cursor_pos: usize, // Stores the cursor position of new playlist to be created
-    pub pl_new_pl_songs_buffer: Vec<Song>, // Buffer for songs that need to be added to the newly created playlist
+    pub pl_new_pl_songs_buffer: Vec<Source>, // Buffer for songs that need to be added to the newly created playlist
 
     // playlist variables
     // used to show playlist popup
     pub playlist_popup: bool,
     pub append_list: ContentList<String>,
-
-    // Determines if the database should be updated or not
-    pub should_update_song_list: bool,
+    pub pl_contents: HashMap<String, Vec<Song>>, // Songs of the stored playlists, fetched when first shown
+    pub pl_songs: ContentList<Song>, // Songs of the highlighted playlist, while they are focused
+    pub doctor_popup: bool,          // Shows the songs of the playlists missing from the database
+    pub dead_entries: ContentList<DeadEntry>, // Those songs, with where they likely went
+    pub pl_content_focus: bool,      // Keys go to the songs of the playlist instead of the list
 
     // States
     pub queue_state: TableState,
     pub browser_state: TableState,
+    pub library_state: TableState,
+    pub search_state: TableState,
     pub playlists_state: ListState,
+    pub pl_content_state: TableState,
+}
+
+/// What the name typed in the playlist prompt is for
+#[derive(Debug, PartialEq, Clone)]
+pub enum PlaylistPrompt {
+    Rename,
+    SaveQueue,
+    /// Copy the named playlist
+    Duplicate(String),
+    /// Write the named playlist, or the queue, to a file
+    Export(Option<String>),
+    /// Read a file into a new playlist
+    Import,
+}
+
+/// Action on the stored playlists, run once the user confirms it
+#[derive(Debug, PartialEq, Clone)]
+pub enum Confirmation {
+    DeletePlaylists(Vec<String>),
+    ClearPlaylists(Vec<String>),
+    /// Saving the queue under the name of an existing playlist
+    SaveQueue(String),
+    /// Removing every song of the playlists missing from the database
+    PruneDeadEntries(usize),
+}
+
+impl Confirmation {
+    /// Question shown in the status line
+    pub fn prompt(&self) -> String {
+        let describe = |names: &[String]| match names {
+            [name] => format!("playlist \"{}\"", name),
+            _ => format!("{} playlists", names.len()),
+        };
+        match self {
+            Confirmation::DeletePlaylists(names) => format!("Delete {}? [y/n]", describe(names)),
+            Confirmation::ClearPlaylists(names) => {
+                format!("Remove every song of {}? [y/n]", describe(names))
+            }
+            Confirmation::PruneDeadEntries(count) => {
+                format!("Remove {} missing songs from the playlists? [y/n]", count)
+            }
+            Confirmation::SaveQueue(name) => format!(
+                "Playlist \"{}\" exists: [a]ppend the queue to it, [r]eplace it, or [n]o?",
+                name
+            ),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -52,95 +141,801 @@ pub enum SelectedTab {
     DirectoryBrowser,
     Queue,
     Playlists,
+    Library,
+    Search,
 }
 
-impl App {
-    pub fn builder(addrs: &str) -> AppResult<Self> {
-        let mut conn = Connection::builder(addrs)?;
-        let mut queue_list = ContentList::new();
-        let mut pl_list = ContentList::new();
+impl FromStr for SelectedTab {
+    type Err = String;
 
-        pl_list.list = Self::get_playlist(&mut conn.conn)?;
-        pl_list.list.sort();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "queue" => Ok(SelectedTab::Queue),
+            "browser" | "directory" => Ok(SelectedTab::DirectoryBrowser),
+            "playlists" => Ok(SelectedTab::Playlists),
+            "library" => Ok(SelectedTab::Library),
+            "search" => Ok(SelectedTab::Search),
+            _ => Err(format!(
+                "unknown tab \"{}\", expected queue, browser, library, playlists or search",
+                s
+            )),
+        }
+    }
+}
 
-        let append_list = Self::get_append_list(&mut conn.conn)?;
-        Self::get_queue(&mut conn, &mut queue_list.list);
+impl App {
+    pub fn builder(address: MpdAddress, config: Config) -> AppResult<Self> {
+        let adder = Adder::new(address.clone());
+        let conn = Connection::builder(address)?;
+        let queue_list = ContentList::new();
+        let pl_list = ContentList::new();
+        let append_list = ContentList::new();
 
-        let browser = FileBrowser::new();
+        let browser = FileBrowser::new(config.audio_extensions.clone());
+        let selected_tab = config.default_tab.clone();
 
         let queue_state = TableState::new();
         let browser_state = TableState::new();
+        let library_state = TableState::new();
+        let search_state = TableState::new();
         let playlists_state = ListState::default();
 
-        Ok(Self {
+        let mut app = Self {
             running: true,
+            config,
             conn,
             queue_list,
             pl_list,
-            selected_tab: SelectedTab::Queue,
+            selected_tab,
+            error_message: None,
+            pending_keys: Vec::new(),
+            adder,
             browser,
+            library: LibraryBrowser::new(),
+            search_results: ContentList::new(),
             inputmode: InputMode::Normal,
             search_input: String::new(),
             pl_newname_input: String::new(),
+            pl_name_prompt: PlaylistPrompt::Rename,
+            confirmation: None,
             search_cursor_pos: 0,
+            last_search: String::new(),
+            search_mode: SearchMode::default(),
+            search_case_sensitive: false,
+            db_search_input: String::new(),
+            db_search_cursor_pos: 0,
+            db_search_query: String::new(),
+            filter_input: String::new(),
+            filter_cursor_pos: 0,
+            queue_filter: None,
+            browser_filter: None,
+            pl_filter: None,
+            selection: Selection::default(),
             pl_cursor_pos: 0,
             playlist_popup: false,
             pl_new_pl_input: String::new(),
             pl_new_pl_cursor_pos: 0,
             pl_new_pl_songs_buffer: Vec::new(),
             append_list,
-            should_update_song_list: false,
+            pl_contents: HashMap::new(),
+            pl_songs: ContentList::new(),
+            doctor_popup: false,
+            dead_entries: ContentList::new(),
+            pl_content_focus: false,
             queue_state,
             browser_state,
+            library_state,
+            search_state,
             playlists_state,
-        })
+            pl_content_state: TableState::new(),
+        };
+
+        if app.conn.conn_state == ConnectionState::Connected {
+            if let Err(err) = app.restore() {
+                app.handle_error(err)?;
+            }
+        }
+
+        Ok(app)
     }
 
     pub fn tick(&mut self) -> AppResult<()> {
-        self.conn.update_status();
-        self.update_queue();
+        self.poll_adder();
+        if self.conn.reconnect() {
+            self.restore()?;
+        }
 
-        // Deals with database update
-        if self.should_update_song_list && self.conn.status.updating_db.is_none() {
+        self.conn.tick()
+    }
+
+    /// Reload everything shown in the views after (re)connecting to MPD
+    pub fn restore(&mut self) -> AppResult<()> {
+        self.update_queue()?;
+        self.update_playlists()?;
+
+        // The directory we were browsing may be gone after a database update
+        self.browser.clear_cache();
+        if self.browser.update_directory(&mut self.conn).is_err() {
+            self.browser = FileBrowser::new(self.config.audio_extensions.clone());
+            self.browser.update_directory(&mut self.conn)?;
+        }
+        self.browser.clamp_selected();
+        self.refresh_filter(SelectedTab::DirectoryBrowser);
+
+        // Same for the artist or album of the library
+        if self.library.update(&mut self.conn).is_err() {
+            self.library.reset(&mut self.conn)?;
+        }
+
+        Ok(())
+    }
+
+    /// Deals with an error returned by one of the event handlers
+    ///
+    /// Losing the connection to MPD is not fatal, the reconnection is handled on
+    /// the following ticks. Commands refused by MPD (e.g. for lack of permission)
+    /// are shown to the user; anything else is passed back to the caller.
+    pub fn handle_error(&mut self, err: Box<dyn Error>) -> AppResult<()> {
+        if is_connection_error(err.as_ref()) {
+            self.conn.disconnect();
+            Ok(())
+        } else if let Some(mpd::error::Error::Server(_)) = err.downcast_ref() {
+            self.error_message = Some(describe_error(err.as_ref()));
+            Ok(())
+        } else {
+            Err(err)
+        }
+    }
+
+    /// Refreshes the state affected by a change reported by MPD's `idle` command
+    pub fn handle_mpd_event(&mut self, subsystem: Subsystem) -> AppResult<()> {
+        self.poll_adder();
+        match subsystem {
+            Subsystem::Queue => {
+                self.update_queue()?;
+                self.conn.update_status()?;
+            }
+
+            Subsystem::Playlist => {
+                self.update_playlists()?;
+            }
+
+            Subsystem::Database => {
                 // Update the songs list
                 self.conn.songs_filenames = self
                     .conn
-                    .conn
+                    .client()?
                     .listall()?
                     .into_iter()
                     .map(|x| x.file)
                     .collect();
 
+                self.browser.clear_cache();
                 self.browser.update_directory(&mut self.conn)?;
+                self.refresh_filter(SelectedTab::DirectoryBrowser);
+                self.library.update(&mut self.conn)?;
+                self.conn.update_status()?;
+            }
 
-                self.should_update_song_list = false;
+            _ => self.conn.update_status()?,
         }
 
         Ok(())
     }
 
+    /// Take the progress of the songs being added, showing what MPD refused
+    pub fn poll_adder(&mut self) {
+        if let Some(message) = self.adder.poll() {
+            self.error_message = Some(message);
+        }
+    }
+
     pub fn quit(&mut self) {
         self.running = false;
     }
 
-    pub fn get_queue(conn: &mut Connection, vec: &mut Vec<Song>) {
-        conn.conn.queue().unwrap().into_iter().for_each(|x| {
-            vec.push(x);
+    /// Sync queue_list with the queue, fetching only the songs changed since the last sync
+    pub fn update_queue(&mut self) -> AppResult<()> {
+        let status = self.conn.client()?.status()?;
+        if status.queue_version == self.conn.queue_version {
+            return Ok(());
+        }
+
+        // plchanges returns the changed songs ordered by position, so anything past
+        // the end of our copy of the queue can simply be appended
+        let queue_version = self.conn.queue_version;
+        for song in self.conn.client()?.changes(queue_version)? {
+            let pos = song.place.unwrap_or_default().pos as usize;
+            match self.queue_list.list.get_mut(pos) {
+                Some(item) => *item = song,
+                None => self.queue_list.list.push(song),
+            }
+        }
+        self.queue_list.list.truncate(status.queue_len as usize);
+        self.queue_list.clamp_index();
+        if self.selected_tab == SelectedTab::Queue {
+            self.selection.truncate(self.queue_list.list.len());
+        }
+
+        self.conn.queue_version = status.queue_version;
+        self.refresh_filter(SelectedTab::Queue);
+        Ok(())
+    }
+
+    /// Rescan the stored playlists into pl_list and append_list
+    pub fn update_playlists(&mut self) -> AppResult<()> {
+        // MPD does not tell which playlists changed
+        self.pl_contents.clear();
+        self.pl_list.list = Self::get_playlist(self.conn.client()?)?;
+        self.pl_list.list.sort();
+        self.pl_list.clamp_index();
+        if self.selected_tab == SelectedTab::Playlists {
+            self.selection.truncate(self.pl_list.list.len());
+        }
+        self.append_list = Self::get_append_list(self.conn.client()?)?;
+        self.refresh_filter(SelectedTab::Playlists);
+        if self.pl_content_focus {
+            self.update_playlist_songs()?;
+        }
+        self.cache_highlighted_playlist()
+    }
+
+    /// Songs of the playlist, fetched from MPD unless they are cached
+    pub fn playlist_contents(&mut self, name: &str) -> AppResult<&Vec<Song>> {
+        if !self.pl_contents.contains_key(name) {
+            let songs = self.conn.client()?.playlist(name)?;
+            self.pl_contents.insert(name.to_string(), songs);
+        }
+        Ok(&self.pl_contents[name])
+    }
+
+    /// Fetch the songs of the highlighted playlist, if needed, before they are drawn
+    pub fn cache_highlighted_playlist(&mut self) -> AppResult<()> {
+        if self.selected_tab != SelectedTab::Playlists {
+            return Ok(());
+        }
+        match self.pl_list.list.get(self.pl_list.index).cloned() {
+            Some(name) => self.playlist_contents(&name).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Reload the songs of the highlighted playlist, leaving them when it is gone
+    pub fn update_playlist_songs(&mut self) -> AppResult<()> {
+        let Some(name) = self.pl_list.list.get(self.pl_list.index) else {
+            self.pl_content_focus = false;
+            return Ok(());
+        };
+        let name = name.clone();
+        self.pl_songs.list = self.playlist_contents(&name)?.clone();
+        self.pl_songs.clamp_index();
+        Ok(())
+    }
+
+    /// Filter of the tab, `None` for the tabs which can not be filtered
+    pub fn filter_mut(&mut self, tab: &SelectedTab) -> Option<&mut Option<ListFilter>> {
+        match tab {
+            SelectedTab::Queue => Some(&mut self.queue_filter),
+            SelectedTab::DirectoryBrowser => Some(&mut self.browser_filter),
+            SelectedTab::Playlists => Some(&mut self.pl_filter),
+            _ => None,
+        }
+    }
+
+    /// Filter applied to the current tab
+    pub fn filter(&self) -> Option<&ListFilter> {
+        match self.selected_tab {
+            SelectedTab::Queue => self.queue_filter.as_ref(),
+            SelectedTab::DirectoryBrowser => self.browser_filter.as_ref(),
+            SelectedTab::Playlists => self.pl_filter.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Filter the current tab with the query being typed, or remove its filter
+    /// when the query is empty
+    pub fn update_filter(&mut self) {
+        let tab = self.selected_tab.clone();
+        let query = self.filter_input.trim().to_string();
+        let Some(filter) = self.filter_mut(&tab) else {
+            return;
+        };
+        *filter = if query.is_empty() {
+            None
+        } else {
+            Some(ListFilter::new(&query))
+        };
+        self.refresh_filter(tab);
+    }
+
+    /// Apply the filter of the tab again after its list changed, keeping the
+    /// highlighted row visible
+    pub fn refresh_filter(&mut self, tab: SelectedTab) {
+        let rows = self.searchable(&tab).search_texts();
+        let selected = self.searchable(&tab).selected();
+        let Some(Some(filter)) = self.filter_mut(&tab) else {
+            return;
+        };
+        filter.apply(rows.iter().map(String::as_str));
+        if let Some(index) = filter.clamp(selected) {
+            self.searchable_mut(&tab).select(index);
+        }
+    }
+
+    /// List of the tab, for the search and the filter
+    pub fn searchable(&self, tab: &SelectedTab) -> &dyn Searchable {
+        match tab {
+            SelectedTab::Queue => &self.queue_list,
+            SelectedTab::DirectoryBrowser => &self.browser,
+            SelectedTab::Library => &self.library,
+            SelectedTab::Playlists => &self.pl_list,
+            SelectedTab::Search => &self.search_results,
+        }
+    }
+
+    pub fn searchable_mut(&mut self, tab: &SelectedTab) -> &mut dyn Searchable {
+        match tab {
+            SelectedTab::Queue => &mut self.queue_list,
+            SelectedTab::DirectoryBrowser => &mut self.browser,
+            SelectedTab::Library => &mut self.library,
+            SelectedTab::Playlists => &mut self.pl_list,
+            SelectedTab::Search => &mut self.search_results,
+        }
+    }
+
+    /// The query compared with the rows in the current search mode
+    pub fn search_matcher(&self, query: &str) -> Result<Matcher, String> {
+        Matcher::new(query, self.search_mode, self.search_case_sensitive)
+    }
+
+    /// Highlight the row closest to the query being typed, in the current tab
+    pub fn search_as_you_type(&mut self) {
+        if self.search_input.is_empty() {
+            return;
+        }
+        let Ok(matcher) = self.search_matcher(&self.search_input) else {
+            return;
+        };
+        let tab = self.selected_tab.clone();
+        if let Some(index) = self.searchable(&tab).best_match(&matcher, self.filter()) {
+            self.searchable_mut(&tab).select(index);
+        }
+    }
+
+    /// Keep the query of the prompt for `n`/`N`, telling when it matches nothing
+    pub fn confirm_search(&mut self) {
+        self.last_search.clone_from(&self.search_input);
+        if self.last_search.is_empty() {
+            return;
+        }
+        let tab = self.selected_tab.clone();
+        let found = self
+            .search_matcher(&self.last_search)
+            .map(|matcher| self.searchable(&tab).best_match(&matcher, self.filter()));
+        match found {
+            Ok(Some(index)) => self.searchable_mut(&tab).select(index),
+            Ok(None) => {
+                self.error_message = Some(format!("Pattern not found: {}", self.last_search))
+            }
+            Err(e) => self.error_message = Some(e),
+        }
+    }
+
+    /// Highlight the next (or previous) row matching the last search, wrapping around
+    pub fn jump_to_match(&mut self, forward: bool) {
+        if self.last_search.is_empty() {
+            return;
+        }
+        let matcher = match self.search_matcher(&self.last_search) {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                self.error_message = Some(e);
+                return;
+            }
+        };
+        let tab = self.selected_tab.clone();
+        let searchable = self.searchable(&tab);
+        let selected = searchable.selected();
+        let matching: Vec<usize> = searchable
+            .matching_rows(&matcher, self.filter())
+            .into_iter()
+            .map(|(i, _)| i)
+            .collect();
+
+        let found = if forward {
+            matching
+                .iter()
+                .find(|i| **i > selected)
+                .or(matching.first())
+        } else {
+            matching
+                .iter()
+                .rev()
+                .find(|i| **i < selected)
+                .or(matching.last())
+        };
+        match found {
+            Some(index) => self.searchable_mut(&tab).select(*index),
+            None => self.error_message = Some(format!("Pattern not found: {}", self.last_search)),
+        }
+    }
+
+    /// Move the highlight of the current tab to the visible row `pick` returns,
+    /// given the filter and the highlighted row
+    pub fn select_filtered(&mut self, pick: impl Fn(&ListFilter, usize) -> Option<usize>) {
+        let tab = self.selected_tab.clone();
+        let selected = self.searchable(&tab).selected();
+        if let Some(index) = self.filter().and_then(|filter| pick(filter, selected)) {
+            self.searchable_mut(&tab).select(index);
+        }
+    }
+
+    /// Rows the bulk actions apply to: the selection, or else the highlighted row
+    pub fn selected_rows(&self) -> Vec<usize> {
+        let searchable = self.searchable(&self.selected_tab);
+        let cursor = searchable.selected();
+        let len = searchable.row_count();
+        if self.selection.is_empty() {
+            return (cursor < len).then_some(cursor).into_iter().collect();
+        }
+        let filter = self.filter();
+        self.selection
+            .rows(cursor)
+            .into_iter()
+            .filter(|i| *i < len && filter.is_none_or(|filter| filter.position(*i).is_some()))
+            .collect()
+    }
+
+    /// Whether the row of the current tab is part of the selection
+    pub fn is_selected(&self, index: usize) -> bool {
+        let cursor = self.searchable(&self.selected_tab).selected();
+        self.selection.contains(index, cursor)
+    }
+
+    /// Songs of the selected rows, in order. Directories, artists, albums and
+    /// playlists give every song they hold
+    pub fn selection_songs(&mut self) -> AppResult<Vec<Song>> {
+        let mut songs = Vec::new();
+        for index in self.selected_rows() {
+            songs.extend(self.selection_songs_at(index)?);
+        }
+        Ok(songs)
+    }
+
+    /// Songs of the row at the given index of the active tab
+    fn selection_songs_at(&mut self, index: usize) -> AppResult<Vec<Song>> {
+        Ok(match self.selected_tab {
+            SelectedTab::Queue => vec![self.queue_list.list[index].clone()],
+            SelectedTab::Search => vec![self.search_results.list[index].clone()],
+            SelectedTab::Library => self.library.songs_at(index, &mut self.conn)?,
+            SelectedTab::Playlists => {
+                let name = &self.pl_list.list[index];
+                self.conn.client()?.playlist(name)?
+            }
+            SelectedTab::DirectoryBrowser => {
+                let (t, name) = &self.browser.filetree[index];
+                let path = format!("{}/{}", self.browser.path, name);
+                let path = path.strip_prefix("./").unwrap_or(&path).to_string();
+                if t == "file" {
+                    vec![self.conn.get_song_with_only_filename(&path)]
+                } else {
+                    self.conn.songs_under(&path)?
+                }
+            }
+        })
+    }
+
+    /// What adds the selected rows, in order. Directories, artists and albums
+    /// are added by MPD in a single request each
+    pub fn selection_sources(&mut self) -> AppResult<Vec<Source>> {
+        let mut sources = Vec::new();
+        for index in self.selected_rows() {
+            match self.selected_tab {
+                SelectedTab::Library => sources.extend(self.library.source_at(index)),
+                SelectedTab::DirectoryBrowser => {
+                    let (t, name) = &self.browser.filetree[index];
+                    let path = format!("{}/{}", self.browser.path, name);
+                    let path = path.strip_prefix("./").unwrap_or(&path).to_string();
+                    if t == "file" {
+                        sources.push(Source::File(path));
+                    } else {
+                        sources.push(Source::Directory(path));
+                    }
+                }
+                SelectedTab::Queue | SelectedTab::Search | SelectedTab::Playlists => {
+                    let songs = self.selection_songs_at(index)?;
+                    sources.extend(Source::from_songs(&songs));
+                }
+            }
+        }
+        Ok(sources)
+    }
+
+    /// Add the songs of the selection to the queue, or remove them when in the queue
+    pub fn add_selection_to_queue(&mut self) -> AppResult<()> {
+        if self.selected_tab == SelectedTab::Queue {
+            return self.delete_selection();
+        }
+        let sources = self.selection_sources()?;
+        self.adder.add(Destination::Queue, sources);
+        self.selection.clear();
+        Ok(())
+    }
+
+    /// Remove the selected songs from the queue
+    pub fn delete_selection(&mut self) -> AppResult<()> {
+        // From the last one, so that the positions of the others stay the same
+        for index in self.selected_rows().iter().rev() {
+            self.conn.client()?.delete(*index as u32)?;
+        }
+        self.selection.clear();
+        self.update_queue()?;
+        self.conn.update_status()
+    }
+
+    /// Have the selected songs played after the current one: inserted from the
+    /// other tabs, moved within the queue
+    pub fn play_next(&mut self) -> AppResult<()> {
+        if self.selected_tab == SelectedTab::Queue {
+            let rows = self.selected_rows();
+            self.conn.move_next(&rows)?;
+        } else {
+            let songs = self.selection_songs()?;
+            self.conn.insert_next(&songs)?;
+        }
+        self.selection.clear();
+        self.update_queue()?;
+        self.conn.update_status()
+    }
+
+    /// Move the selected songs of the queue one row down or up, keeping them selected
+    pub fn move_selection(&mut self, down: bool) -> AppResult<()> {
+        let rows = self.selected_rows();
+        let len = self.queue_list.list.len();
+        let blocked = if down {
+            rows.last().is_none_or(|last| last + 1 >= len)
+        } else {
+            rows.first().is_none_or(|first| *first == 0)
+        };
+        if blocked {
+            return Ok(());
+        }
+
+        // Songs next to each other move one after the other, the first one making room
+        let client = self.conn.client()?;
+        if down {
+            for index in rows.iter().rev() {
+                client.swap(*index as u32, *index as u32 + 1)?;
+            }
+        } else {
+            for index in rows.iter() {
+                client.swap(*index as u32, *index as u32 - 1)?;
+            }
+        }
+
+        let offset = if down { 1 } else { -1 };
+        self.selection.shift(offset);
+        if rows.contains(&self.queue_list.index) {
+            self.queue_list.index = self.queue_list.index.saturating_add_signed(offset);
+        }
+        self.update_queue()?;
+        self.conn.update_status()
+    }
+
+    /// Names of the selected playlists, or of the highlighted one
+    pub fn selected_playlists(&self) -> Vec<String> {
+        self.selected_rows()
+            .into_iter()
+            .filter_map(|i| self.pl_list.list.get(i).cloned())
+            .collect()
+    }
+
+    /// Ask the user before running the action
+    pub fn ask_confirmation(&mut self, confirmation: Confirmation) {
+        self.confirmation = Some(confirmation);
+        self.inputmode = InputMode::Confirm;
+    }
+
+    /// Run the action waiting for confirmation with the given answer, any
+    /// answer it does not expect cancels it
+    pub fn answer_confirmation(&mut self, answer: char) -> AppResult<()> {
+        self.inputmode = InputMode::Normal;
+        let Some(confirmation) = self.confirmation.take() else {
+            return Ok(());
+        };
+        match (confirmation, answer) {
+            (Confirmation::DeletePlaylists(names), 'y') => {
+                for name in names.iter() {
+                    self.conn.client()?.pl_remove(name)?;
+                }
+                self.selection.clear();
+            }
+            (Confirmation::ClearPlaylists(names), 'y') => {
+                for name in names.iter() {
+                    self.conn.client()?.pl_clear(name)?;
+                }
+                self.selection.clear();
+            }
+            (Confirmation::PruneDeadEntries(_), 'y') => {
+                let entries = self.dead_entries.list.clone();
+                return self.heal_playlists(entries, true);
+            }
+            (Confirmation::SaveQueue(name), 'a') => {
+                let songs = self.queue_list.list.clone();
+                let client = self.conn.client()?;
+                for song in songs.iter() {
+                    client.pl_push(&name, song)?;
+                }
+            }
+            (Confirmation::SaveQueue(name), 'r') => {
+                // Saved under another name first, so that the playlist is only
+                // removed once the queue made it to MPD
+                let mut temporary = format!("{}.saving", name);
+                while self.pl_list.list.contains(&temporary) {
+                    temporary.push('~');
+                }
+                let client = self.conn.client()?;
+                client.save(&temporary)?;
+                if let Err(err) = client.pl_remove(&name) {
+                    let _ = client.pl_remove(&temporary);
+                    return Err(err.into());
+                }
+                client.pl_rename(&temporary, &name)?;
+            }
+            _ => return Ok(()),
+        }
+        self.update_playlists()
+    }
+
+    /// Save the queue as a new playlist, asking what to do when it already exists
+    pub fn save_queue(&mut self, name: &str) -> AppResult<()> {
+        if self.pl_list.list.iter().any(|pl| pl == name) {
+            self.ask_confirmation(Confirmation::SaveQueue(name.to_string()));
+            return Ok(());
+        }
+        self.conn.client()?.save(name)?;
+        self.update_playlists()
+    }
+
+    /// Copy the songs of a playlist into a new one
+    pub fn duplicate_playlist(&mut self, source: &str, name: &str) -> AppResult<()> {
+        if self.pl_list.list.iter().any(|pl| pl == name) {
+            self.error_message = Some(format!("Playlist \"{}\" already exists", name));
+            return Ok(());
+        }
+        let client = self.conn.client()?;
+        let songs = client.playlist(source)?;
+        // Creates the playlist, even when there is nothing to copy
+        client.pl_clear(name)?;
+        for song in songs.iter() {
+            client.pl_push(name, song)?;
+        }
+        self.update_playlists()
+    }
+
+    /// Look for the songs of every stored playlist missing from the database
+    pub fn scan_playlists(&mut self) -> AppResult<()> {
+        let files = self.conn.songs_filenames.clone();
+        let known: HashSet<&str> = files.iter().map(String::as_str).collect();
+        let mut dead = Vec::new();
+        for name in self.pl_list.list.clone() {
+            let songs = self.playlist_contents(&name)?.clone();
+            dead.extend(doctor::dead_entries(&name, &songs, &known));
+        }
+
+        let relocator = Relocator::new(&files);
+        for entry in dead.iter_mut() {
+            entry.suggestion = relocator.relocate(&entry.song, &mut self.conn)?;
+        }
+        self.dead_entries.list = dead;
+        self.dead_entries.clamp_index();
+        Ok(())
+    }
+
+    /// Put the songs back where they were likely moved to, or remove them from
+    /// their playlist when pruning. Songs with no suggestion are only pruned
+    pub fn heal_playlists(&mut self, mut entries: Vec<DeadEntry>, prune: bool) -> AppResult<()> {
+        // From the last one of each playlist, so that the positions of the others stay the same
+        entries.sort_by(|a, b| {
+            a.playlist
+                .cmp(&b.playlist)
+                .then(b.position.cmp(&a.position))
         });
+        for entry in entries {
+            let position = entry.position as u32;
+            match (entry.suggestion, prune) {
+                (_, true) => self.conn.client()?.pl_delete(&entry.playlist, position)?,
+                (Some(file), false) => {
+                    // Replacing a song keeps the length of the playlist, so the cached one holds
+                    let last = self.playlist_contents(&entry.playlist)?.len() as u32;
+                    let song = self.conn.get_song_with_only_filename(&file);
+                    let client = self.conn.client()?;
+                    client.pl_push(&entry.playlist, &song)?;
+                    client.pl_shift(&entry.playlist, last, position)?;
+                    client.pl_delete(&entry.playlist, position + 1)?;
+                }
+                (None, false) => {}
+            }
+        }
+        self.update_playlists()?;
+        self.scan_playlists()
     }
 
-    // Rescan the queue into queue_list
-    pub fn update_queue(&mut self) {
-        self.queue_list.list.clear();
-        Self::get_queue(&mut self.conn, &mut self.queue_list.list);
+    /// Write the songs of the playlist, or of the queue, to a playlist file
+    pub fn export_playlist(&mut self, source: Option<&str>, path: &str) -> AppResult<()> {
+        let songs = match source {
+            Some(name) => self.playlist_contents(name)?.clone(),
+            None => self.queue_list.list.clone(),
+        };
+        // A bad path or extension is not fatal, nor a lost connection to MPD
+        if let Err(err) = playlist_file::export(&playlist_file::expand_home(path), &songs) {
+            self.error_message = Some(format!("Could not export to \"{}\": {}", path, err));
+        }
+        Ok(())
     }
 
-    pub fn get_playlist(conn: &mut Client) -> AppResult<Vec<String>> {
+    /// Read a playlist file into a new playlist named after it, reporting the
+    /// entries missing from the database
+    pub fn import_playlist(&mut self, path: &str) -> AppResult<()> {
+        let file = playlist_file::expand_home(path);
+        let entries = match playlist_file::import(&file) {
+            Ok(entries) => entries,
+            Err(err) => {
+                self.error_message = Some(format!("Could not import \"{}\": {}", path, err));
+                return Ok(());
+            }
+        };
+        let name = file
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default()
+            .to_string();
+        if self.pl_list.list.contains(&name) {
+            self.error_message = Some(format!("Playlist \"{}\" already exists", name));
+            return Ok(());
+        }
+
+        let resolver = Resolver::new(&self.conn.songs_filenames);
+        let mut songs = Vec::new();
+        let mut missing = Vec::new();
+        for entry in entries.iter() {
+            match resolver.resolve(entry) {
+                Some(file) => songs.push(self.conn.get_song_with_only_filename(&file)),
+                None => missing.push(entry.title.as_ref().unwrap_or(&entry.location).clone()),
+            }
+        }
+        self.adder.add(
+            Destination::Playlist(name.clone()),
+            Source::from_songs(&songs),
+        );
+
+        if !missing.is_empty() {
+            let mut shown = missing
+                .iter()
+                .take(3)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ");
+            if missing.len() > 3 {
+                shown.push_str(&format!(" and {} more", missing.len() - 3));
+            }
+            self.error_message = Some(format!(
+                "{} of {} entries not found for \"{}\": {}",
+                missing.len(),
+                entries.len(),
+                name,
+                shown
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn get_playlist(conn: &mut MpdClient) -> AppResult<Vec<String>> {
         let list: Vec<String> = conn.playlists()?.iter().map(|p| p.clone().name).collect();
         Ok(list)
     }
 
-    pub fn get_append_list(conn: &mut Client) -> AppResult<ContentList<String>> {
+    pub fn get_append_list(conn: &mut MpdClient) -> AppResult<ContentList<String>> {
         let mut list = ContentList::new();
         list.list.push("Current Playlist".to_string());
         list.list.push("New Playlist".to_string());
@@ -155,30 +950,23 @@ impl App {
     pub fn handle_add_or_remove_from_current_playlist(&mut self) -> AppResult<()> {
         match self.selected_tab {
             SelectedTab::DirectoryBrowser => {
-                let (content_type, content) =
-                    self.browser.filetree.get(self.browser.selected).unwrap();
+                let Some((content_type, content)) =
+                    self.browser.filetree.get(self.browser.selected)
+                else {
+                    return Ok(());
+                };
                 if content_type == "directory" {
-                    let file = format!("{}/{}", self.browser.path, content);
-                    let songs = self.conn.conn.listfiles(&file).unwrap_or_default();
-                    for (t, f) in songs.iter() {
-                        if t == "file"
-                            && Path::new(&f).has_extension(&[
-                                "mp3", "ogg", "flac", "m4a", "wav", "aac", "opus", "ape", "wma",
-                                "mpc", "aiff", "dff", "mp2", "mka",
-                            ])
-                        {
-                            let path = file.clone() + "/" + f;
-                            let full_path = path.strip_prefix("./").unwrap_or("");
-                            let song = self.conn.get_song_with_only_filename(full_path);
-                            self.conn.conn.push(&song)?;
-                        }
-                    }
+                    // The whole tree, subdirectories included
+                    let path = format!("{}/{}", self.browser.path, content);
+                    let path = path.strip_prefix("./").unwrap_or(&path).to_string();
+                    self.adder
+                        .add(Destination::Queue, vec![Source::Directory(path)]);
                 } else if content_type == "file" {
                     let mut status = false;
                     for (i, song) in self.queue_list.list.clone().iter().enumerate() {
-                        let song_path = song.file.split('/').last().unwrap_or_default();
+                        let song_path = song.file.split('/').next_back().unwrap_or_default();
                         if song_path.eq(content) {
-                            self.conn.conn.delete(i as u32).unwrap();
+                            self.conn.client()?.delete(i as u32)?;
                             status = true;
                         }
                     }
@@ -191,19 +979,42 @@ impl App {
                         filename.remove(0);
 
                         let song = self.conn.get_song_with_only_filename(&filename);
-                        self.conn.conn.push(&song)?;
+                        self.conn.client()?.push(&song)?;
 
                         // updating queue, to avoid multiple pushes of the same songs if we enter multiple times before the queue gets updated
-                        self.update_queue();
+                        self.update_queue()?;
                     }
                 }
 
                 // Highlight next row if possible
-                if self.browser.selected != self.browser.filetree.len() - 1 {
+                if self.browser.selected != self.browser.filetree.len().saturating_sub(1) {
                     self.browser.selected += 1;
                 }
             }
 
+            SelectedTab::Library => {
+                if self.library.level == LibraryLevel::Tracks {
+                    // A track already in the queue is removed instead, like in the directory browser
+                    if let Some(song) = self.library.songs.get(self.library.selected).cloned() {
+                        self.toggle_in_queue(&song)?;
+                    }
+                } else if let Some(source) = self.library.source_at(self.library.selected) {
+                    self.adder.add(Destination::Queue, vec![source]);
+                }
+
+                // Highlight next row if possible
+                self.library.next();
+            }
+
+            SelectedTab::Search => {
+                let Some(song) = self.search_results.list.get(self.search_results.index) else {
+                    return Ok(());
+                };
+                let song = song.clone();
+                self.toggle_in_queue(&song)?;
+                self.search_results.next();
+            }
+
             SelectedTab::Queue => {
                 if self.queue_list.list.is_empty() {
                     return Ok(());
@@ -218,7 +1029,7 @@ impl App {
 
                 for (i, song) in self.queue_list.list.clone().iter().enumerate() {
                     if song.file.eq(&file) {
-                        self.conn.conn.delete(i as u32).unwrap();
+                        self.conn.client()?.delete(i as u32)?;
                         if self.queue_list.index == self.queue_list.list.len() - 1
                             && self.queue_list.index != 0
                         {
@@ -231,8 +1042,8 @@ impl App {
             _ => {}
         }
 
-        self.update_queue();
-        self.conn.update_status();
+        self.update_queue()?;
+        self.conn.update_status()?;
         Ok(())
     }
 
@@ -240,15 +1051,19 @@ impl App {
     pub fn cycle_tabls(&mut self) {
         self.selected_tab = match self.selected_tab {
             SelectedTab::Queue => SelectedTab::DirectoryBrowser,
-            SelectedTab::DirectoryBrowser => SelectedTab::Playlists,
-            SelectedTab::Playlists => SelectedTab::DirectoryBrowser,
+            SelectedTab::DirectoryBrowser => SelectedTab::Library,
+            SelectedTab::Library => SelectedTab::Playlists,
+            SelectedTab::Playlists => SelectedTab::Search,
+            SelectedTab::Search => SelectedTab::DirectoryBrowser,
         };
     }
 
     /// handles the Enter event on the directory browser
     pub fn handle_enter(&mut self) -> AppResult<()> {
         let browser = &mut self.browser;
-        let (t, path) = browser.filetree.get(browser.selected).unwrap();
+        let Some((t, path)) = browser.filetree.get(browser.selected) else {
+            return Ok(());
+        };
         if t == "directory" {
             if path != "." {
                 browser.prev_path.clone_from(&browser.path);
@@ -259,12 +1074,14 @@ impl App {
             }
         } else {
             let index = self.queue_list.list.iter().position(|x| {
-                let file = x.file.split('/').last().unwrap_or_default();
+                let file = x.file.split('/').next_back().unwrap_or_default();
                 file.eq(path)
             });
 
-            if index.is_some() {
-                self.conn.conn.switch(index.unwrap() as u32)?;
+            if let Some(index) = index {
+                if self.config.add_mode == AddMode::Play {
+                    self.conn.client()?.switch(index as u32)?;
+                }
             } else {
                 let mut filename = format!("{}/{}", browser.path, path);
 
@@ -273,15 +1090,133 @@ impl App {
                 filename.remove(0);
 
                 let song = self.conn.get_song_with_only_filename(&filename);
-                self.conn.push(&song)?;
+                self.enter_song(&song)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// handles the Enter event on the library browser
+    pub fn handle_library_enter(&mut self) -> AppResult<()> {
+        if self.library.level != LibraryLevel::Tracks {
+            return self.library.handle_enter(&mut self.conn);
+        }
+        match self.library.songs.get(self.library.selected) {
+            Some(song) => self.enter_song(&song.clone()),
+            None => Ok(()),
+        }
+    }
+
+    /// Play the song picked with Enter, or only add it, depending on `add_mode`
+    pub fn enter_song(&mut self, song: &Song) -> AppResult<()> {
+        if self.config.add_mode == AddMode::Play {
+            return self.play_song(song);
+        }
+        if !self.queue_list.list.iter().any(|x| x.file == song.file) {
+            self.conn.client()?.push(song)?;
+            self.update_queue()?;
+        }
+        Ok(())
+    }
+
+    /// Add the songs of the selection at the end of the queue, and play the
+    /// first one if asked to
+    pub fn add_selection(&mut self, play: bool) -> AppResult<()> {
+        let mut first = None;
+        for song in self.selection_songs()? {
+            let id = self.conn.client()?.push(&song)?;
+            first.get_or_insert(id);
+        }
+        if let (true, Some(id)) = (play, first) {
+            self.conn.client()?.switch(id)?;
+        }
+        self.selection.clear();
+        self.update_queue()?;
+        self.conn.update_status()
+    }
 
-                // updating queue, to avoid multiple pushes of the same songs if we enter multiple times before the queue gets updated
-                self.update_queue();
+    /// Play the song, adding it to the queue when it is not there yet
+    pub fn play_song(&mut self, song: &Song) -> AppResult<()> {
+        let index = self
+            .queue_list
+            .list
+            .iter()
+            .position(|x| x.file == song.file);
+        if let Some(index) = index {
+            self.conn.client()?.switch(index as u32)?;
+        } else {
+            self.conn.push(song)?;
+
+            // updating queue, to avoid multiple pushes of the same songs if we enter multiple times before the queue gets updated
+            self.update_queue()?;
+        }
+        Ok(())
+    }
+
+    /// Add the song to the queue, or remove it when it is already there
+    pub fn toggle_in_queue(&mut self, song: &Song) -> AppResult<()> {
+        let queued: Vec<usize> = self
+            .queue_list
+            .list
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.file == song.file)
+            .map(|(i, _)| i)
+            .collect();
+
+        if queued.is_empty() {
+            self.conn.client()?.push(song)?;
+        } else {
+            for i in queued.iter().rev() {
+                self.conn.client()?.delete(*i as u32)?;
             }
         }
         Ok(())
     }
 
+    /// Search the database for the query typed in the prompt, and show the results
+    pub fn run_database_search(&mut self) -> AppResult<()> {
+        let exprs = match search::parse_query(&self.db_search_input) {
+            Ok(exprs) => exprs,
+            Err(err) => {
+                self.error_message = Some(err);
+                return Ok(());
+            }
+        };
+
+        self.search_results.list = search::search(self.conn.client()?, &exprs)?;
+        self.search_results.reset_index();
+        self.selection.clear();
+        self.db_search_query.clone_from(&self.db_search_input);
+        self.selected_tab = SelectedTab::Search;
+        Ok(())
+    }
+
+    /// Add every song under the highlighted library item and play the first one
+    pub fn play_library_selection(&mut self) -> AppResult<()> {
+        let tags = self.library.tags_at(self.library.selected);
+        let track = match self.library.level {
+            LibraryLevel::Tracks => self.library.songs.get(self.library.selected).cloned(),
+            _ => None,
+        };
+
+        // The first of the songs added is where the queue used to end
+        let client = self.conn.client()?;
+        let first = client.status()?.queue_len;
+        match (tags, track) {
+            (Some(tags), _) => client.findadd(&library::tag_query(&tags))?,
+            (None, Some(song)) => {
+                client.push(&song)?;
+            }
+            (None, None) => return Ok(()),
+        }
+        if client.status()?.queue_len > first {
+            client.switch(first)?;
+            self.update_queue()?;
+        }
+        Ok(())
+    }
+
     // Cursor movements
     pub fn move_cursor_left(&mut self) {
         match self.inputmode {
@@ -297,6 +1232,14 @@ impl App {
                 let cursor_moved_left = self.pl_new_pl_cursor_pos.saturating_sub(1);
                 self.pl_new_pl_cursor_pos = self.clamp_cursor(cursor_moved_left);
             }
+            InputMode::DatabaseSearch => {
+                let cursor_moved_left = self.db_search_cursor_pos.saturating_sub(1);
+                self.db_search_cursor_pos = self.clamp_cursor(cursor_moved_left);
+            }
+            InputMode::Filter => {
+                let cursor_moved_left = self.filter_cursor_pos.saturating_sub(1);
+                self.filter_cursor_pos = self.clamp_cursor(cursor_moved_left);
+            }
             _ => {}
         }
     }
@@ -317,6 +1260,16 @@ impl App {
                 self.pl_new_pl_cursor_pos = self.clamp_cursor(cursor_moved_right);
             }
 
+            InputMode::DatabaseSearch => {
+                let cursor_moved_right = self.db_search_cursor_pos.saturating_add(1);
+                self.db_search_cursor_pos = self.clamp_cursor(cursor_moved_right);
+            }
+
+            InputMode::Filter => {
+                let cursor_moved_right = self.filter_cursor_pos.saturating_add(1);
+                self.filter_cursor_pos = self.clamp_cursor(cursor_moved_right);
+            }
+
             _ => {}
         }
     }
@@ -324,14 +1277,24 @@ impl App {
     pub fn enter_char(&mut self, new_char: char) {
         match self.inputmode {
             InputMode::PlaylistRename => {
-                self.pl_newname_input.insert(self.pl_cursor_pos, new_char);
+                let index = byte_index(&self.pl_newname_input, self.pl_cursor_pos);
+                self.pl_newname_input.insert(index, new_char);
             }
             InputMode::NewPlaylist => {
-                self.pl_new_pl_input
-                    .insert(self.pl_new_pl_cursor_pos, new_char);
+                let index = byte_index(&self.pl_new_pl_input, self.pl_new_pl_cursor_pos);
+                self.pl_new_pl_input.insert(index, new_char);
             }
             InputMode::Editing => {
-                self.search_input.insert(self.search_cursor_pos, new_char);
+                let index = byte_index(&self.search_input, self.search_cursor_pos);
+                self.search_input.insert(index, new_char);
+            }
+            InputMode::DatabaseSearch => {
+                let index = byte_index(&self.db_search_input, self.db_search_cursor_pos);
+                self.db_search_input.insert(index, new_char);
+            }
+            InputMode::Filter => {
+                let index = byte_index(&self.filter_input, self.filter_cursor_pos);
+                self.filter_input.insert(index, new_char);
             }
             _ => {}
         }
@@ -344,6 +1307,8 @@ impl App {
             InputMode::PlaylistRename => self.pl_cursor_pos != 0,
             InputMode::NewPlaylist => self.pl_new_pl_cursor_pos != 0,
             InputMode::Editing => self.search_cursor_pos != 0,
+            InputMode::DatabaseSearch => self.db_search_cursor_pos != 0,
+            InputMode::Filter => self.filter_cursor_pos != 0,
             _ => false,
         };
 
@@ -356,6 +1321,8 @@ impl App {
                 InputMode::Editing => self.search_cursor_pos,
                 InputMode::PlaylistRename => self.pl_cursor_pos,
                 InputMode::NewPlaylist => self.pl_new_pl_cursor_pos,
+                InputMode::DatabaseSearch => self.db_search_cursor_pos,
+                InputMode::Filter => self.filter_cursor_pos,
                 _ => 0,
             };
 
@@ -386,15 +1353,37 @@ impl App {
 
                 self.search_input = before_char_to_delete.chain(after_char_to_delete).collect();
                 self.move_cursor_left();
+            } else if self.inputmode == InputMode::DatabaseSearch {
+                let before_char_to_delete = self
+                    .db_search_input
+                    .chars()
+                    .take(from_left_to_current_index);
+                let after_char_to_delete = self.db_search_input.chars().skip(current_index);
+
+                self.db_search_input = before_char_to_delete.chain(after_char_to_delete).collect();
+                self.move_cursor_left();
+            } else if self.inputmode == InputMode::Filter {
+                let before_char_to_delete =
+                    self.filter_input.chars().take(from_left_to_current_index);
+                let after_char_to_delete = self.filter_input.chars().skip(current_index);
+
+                self.filter_input = before_char_to_delete.chain(after_char_to_delete).collect();
+                self.move_cursor_left();
             }
         }
     }
 
     pub fn clamp_cursor(&self, new_cursor_pos: usize) -> usize {
         match self.inputmode {
-            InputMode::PlaylistRename => new_cursor_pos.clamp(0, self.pl_newname_input.len()),
-            InputMode::NewPlaylist => new_cursor_pos.clamp(0, self.pl_new_pl_input.len()),
-            InputMode::Editing => new_cursor_pos.clamp(0, self.search_input.len()),
+            InputMode::PlaylistRename => {
+                new_cursor_pos.clamp(0, self.pl_newname_input.chars().count())
+            }
+            InputMode::NewPlaylist => new_cursor_pos.clamp(0, self.pl_new_pl_input.chars().count()),
+            InputMode::Editing => new_cursor_pos.clamp(0, self.search_input.chars().count()),
+            InputMode::DatabaseSearch => {
+                new_cursor_pos.clamp(0, self.db_search_input.chars().count())
+            }
+            InputMode::Filter => new_cursor_pos.clamp(0, self.filter_input.chars().count()),
             _ => 0,
         }
     }
@@ -410,6 +1399,12 @@ impl App {
             InputMode::NewPlaylist => {
                 self.pl_new_pl_cursor_pos = 0;
             }
+            InputMode::DatabaseSearch => {
+                self.db_search_cursor_pos = 0;
+            }
+            InputMode::Filter => {
+                self.filter_cursor_pos = 0;
+            }
             _ => {}
         }
     }
@@ -429,6 +1424,10 @@ impl App {
 
     // Mouse event handlers
     pub fn handle_scroll_up(&mut self) {
+        if self.filter().is_some() {
+            self.select_filtered(ListFilter::prev);
+            return;
+        }
         match self.selected_tab {
             SelectedTab::Queue => {
                 self.queue_list.prev();
@@ -436,6 +1435,12 @@ impl App {
             SelectedTab::DirectoryBrowser => {
                 self.browser.prev();
             }
+            SelectedTab::Library => {
+                self.library.prev();
+            }
+            SelectedTab::Search => {
+                self.search_results.prev();
+            }
             SelectedTab::Playlists => {
                 self.pl_list.prev();
             }
@@ -443,6 +1448,10 @@ impl App {
     }
 
     pub fn handle_scroll_down(&mut self) {
+        if self.filter().is_some() {
+            self.select_filtered(ListFilter::next);
+            return;
+        }
         match self.selected_tab {
             SelectedTab::Queue => {
                 self.queue_list.next();
@@ -450,6 +1459,12 @@ impl App {
             SelectedTab::DirectoryBrowser => {
                 self.browser.next();
             }
+            SelectedTab::Library => {
+                self.library.next();
+            }
+            SelectedTab::Search => {
+                self.search_results.next();
+            }
             SelectedTab::Playlists => {
                 self.pl_list.next();
             }
@@ -460,3 +1475,11 @@ impl App {
         Ok(())
     }
 }
+
+/// Byte position in the text of the cursor, which counts characters
+fn byte_index(text: &str, cursor: usize) -> usize {
+    text.char_indices()
+        .map(|(i, _)| i)
+        .nth(cursor)
+        .unwrap_or(text.len())
+}
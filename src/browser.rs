@@ -1,9 +1,12 @@
-use std::path::Path;
+use std::{cmp::Ordering, collections::HashMap, path::Path, time::Duration};
 
 use mpd::Song;
 
 use crate::{app::AppResult, connection::Connection, utils::FileExtension};
 
+/// Entries of a directory and the metadata of its songs
+type Listing = (Vec<(String, String)>, Vec<Song>);
+
 #[derive(Debug)]
 /// struct for working with directory browser tab in rmptui
 pub struct FileBrowser {
@@ -13,10 +16,12 @@ pub struct FileBrowser {
     pub path: String,
     pub prev_path: String,
     pub songs: Vec<Song>,
+    pub extensions: Vec<String>, // Files with other extensions are not listed
+    cache: HashMap<String, Listing>, // Listed directories, by path
 }
 
 impl FileBrowser {
-    pub fn new() -> FileBrowser {
+    pub fn new(extensions: Vec<String>) -> FileBrowser {
         FileBrowser {
             filetree: Vec::new(),
             selected: 0,
@@ -24,83 +29,78 @@ impl FileBrowser {
             path: ".".to_string(),
             prev_path: ".".to_string(),
             songs: vec![],
+            extensions,
+            cache: HashMap::new(),
         }
     }
 
+    /// List the directory at `path`, from the cache when it was listed before
     pub fn update_directory(&mut self, conn: &mut Connection) -> AppResult<()> {
-        self.filetree.clear();
-        let mut file_vec: Vec<(String, String)> = vec![];
-        let mut dir_vec: Vec<(String, String)> = vec![];
-        for (t, f) in conn.conn.listfiles(self.path.as_str())?.into_iter() {
-            if t == "directory" && !f.starts_with('.') {
-                dir_vec.push((t, f));
-            } else if t == "file"
-                && Path::new(&f).has_extension(&[
-                    "mp3", "ogg", "flac", "m4a", "wav", "aac", "opus", "ape", "wma", "mpc", "aiff",
-                    "dff", "mp2", "mka",
-                ])
-            {
-                file_vec.push((t, f));
-            }
+        if let Some((filetree, songs)) = self.cache.get(&self.path) {
+            self.filetree.clone_from(filetree);
+            self.songs.clone_from(songs);
+            return Ok(());
         }
 
-        // dir_vec.sort_by(|a, b| a.1.cmp(&b.1));
-        dir_vec.sort_by(|a, b| {
-            let num_a = a.1.parse::<u32>().unwrap_or(u32::MAX);
-            let num_b = b.1.parse::<u32>().unwrap_or(u32::MAX);
-            num_a
-                .cmp(&num_b)
-                .then_with(|| a.1.to_lowercase().cmp(&b.1.to_lowercase()))
-        });
-
-        file_vec.sort_by(|a, b| {
-            let num_a = a.1.parse::<u32>().unwrap_or(u32::MAX);
-            let num_b = b.1.parse::<u32>().unwrap_or(u32::MAX);
-            num_a
-                .cmp(&num_b)
-                .then_with(|| a.1.to_lowercase().cmp(&b.1.to_lowercase()))
-        });
-
-        dir_vec.extend(file_vec);
-        self.filetree = dir_vec;
-
-        // Add metadata
-        self.songs.clear();
-        for (t, song) in self.filetree.iter() {
-            if t == "file" {
-                let v = conn
-                    .conn
-                    .lsinfo(Song {
-                        file: (self.path.clone() + "/" + song)
-                            .strip_prefix("./")
-                            .unwrap_or("")
-                            .to_string(),
-                        ..Default::default()
-                    })
-                    .unwrap_or_else(|_| {
-                        vec![Song {
-                            file: "Not a song".to_string(),
+        // A single lsinfo gives the entries and the metadata of the songs, the
+        // mpd crate drops the directories listed before the first song so the
+        // pairs are read as they come
+        let directory = self.path.strip_prefix('.').unwrap_or(&self.path);
+        let directory = directory.strip_prefix('/').unwrap_or(directory);
+        let pairs = conn.address.request("lsinfo", directory)?;
+
+        let mut dirs: Vec<(String, String)> = vec![];
+        let mut files: Vec<(String, Song)> = vec![];
+        let mut song: Option<Song> = None;
+        for (key, value) in pairs {
+            match key.as_str() {
+                "directory" | "file" | "playlist" => {
+                    files.extend(song.take().map(|song| (file_name(&song.file), song)));
+                    if key == "directory" {
+                        dirs.push((key, file_name(&value)));
+                    } else if key == "file" {
+                        song = Some(Song {
+                            file: value,
                             ..Default::default()
-                        }]
-                    });
-
-                self.songs.push(v.first().unwrap().clone());
-            } else {
-                let v = Song {
-                    file: "".to_string(),
-                    ..Default::default()
-                };
-
-                self.songs.push(v);
+                        });
+                    }
+                }
+                _ => {
+                    if let Some(song) = song.as_mut() {
+                        set_field(song, key, value);
+                    }
+                }
             }
         }
+        files.extend(song.map(|song| (file_name(&song.file), song)));
+
+        dirs.retain(|(_, name)| !name.starts_with('.'));
+        files.retain(|(name, _)| Path::new(name).has_extension(&self.extensions));
+        dirs.sort_by(|a, b| compare_names(&a.1, &b.1));
+        files.sort_by(|a, b| compare_names(&a.0, &b.0));
+
+        let mut songs = vec![Song::default(); dirs.len()];
+        let mut filetree = dirs;
+        for (name, song) in files {
+            filetree.push(("file".to_string(), name));
+            songs.push(song);
+        }
 
+        self.cache
+            .insert(self.path.clone(), (filetree.clone(), songs.clone()));
+        self.filetree = filetree;
+        self.songs = songs;
         Ok(())
     }
 
+    /// Forget the listed directories, after the database changed
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
     // Go to next item in filetree
     pub fn next(&mut self) {
-        if self.selected < self.filetree.len() - 1 {
+        if self.selected < self.filetree.len().saturating_sub(1) {
             self.selected += 1;
         }
     }
@@ -112,6 +112,13 @@ impl FileBrowser {
         }
     }
 
+    /// Keep the selection inside the filetree after it shrinks
+    pub fn clamp_selected(&mut self) {
+        if self.selected >= self.filetree.len() {
+            self.selected = self.filetree.len().saturating_sub(1);
+        }
+    }
+
     /// handles going back event
     pub fn handle_go_back(&mut self, conn: &mut Connection) -> AppResult<()> {
         if self.prev_path != "." {
@@ -130,8 +137,28 @@ impl FileBrowser {
     }
 }
 
-impl Default for FileBrowser {
-    fn default() -> Self {
-        Self::new()
+/// Last component of a path of the database
+fn file_name(path: &str) -> String {
+    path.rsplit('/').next().unwrap_or(path).to_string()
+}
+
+/// Fill in the song with a pair of its lsinfo entry, the way the mpd crate does
+fn set_field(song: &mut Song, key: String, value: String) {
+    match key.as_str() {
+        "Title" => song.title = Some(value),
+        "Artist" => song.artist = Some(value),
+        "Name" => song.name = Some(value),
+        "Last-Modified" => song.last_mod = Some(value),
+        "Time" => song.duration = value.parse().ok().map(Duration::from_secs),
+        _ => song.tags.push((key, value)),
     }
 }
+
+/// Numbers first, in numeric order, then the other names alphabetically
+fn compare_names(a: &str, b: &str) -> Ordering {
+    let num_a = a.parse::<u32>().unwrap_or(u32::MAX);
+    let num_b = b.parse::<u32>().unwrap_or(u32::MAX);
+    num_a
+        .cmp(&num_b)
+        .then_with(|| a.to_lowercase().cmp(&b.to_lowercase()))
+}
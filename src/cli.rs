@@ -0,0 +1,108 @@
+use crate::address::{Endpoint, MpdAddress};
+use crate::app::{AppResult, SelectedTab};
+use std::path::PathBuf;
+
+const USAGE: &str = "\
+Usage: rmptui [OPTIONS]
+
+Options:
+  -h, --host <HOST>          MPD host, [password@]host[:port] (default: $MPD_HOST)
+  -p, --port <PORT>          MPD port (default: $MPD_PORT or 6600)
+  -P, --password <PASSWORD>  MPD password
+  -s, --socket <PATH>        Connect to a Unix socket instead, @name for an abstract one
+  -t, --tab <TAB>            Tab to open on startup: queue, browser, library, playlists or search
+  -c, --config <PATH>        Path of the configuration file
+  -V, --version              Print version
+      --help                 Print help";
+
+#[derive(Debug, Default)]
+/// Options given on the command line
+pub struct Args {
+    pub host: Option<String>,
+    pub port: Option<String>,
+    pub password: Option<String>,
+    pub socket: Option<String>,
+    pub tab: Option<SelectedTab>,
+    pub config: Option<PathBuf>,
+}
+
+impl Args {
+    /// Parse the arguments the program was started with
+    ///
+    /// `--help` and `--version` print to stdout and exit right away, invalid
+    /// arguments print the usage to stderr and exit with an error.
+    pub fn parse() -> Self {
+        match Self::parse_from(std::env::args().skip(1)) {
+            Ok(Some(args)) => args,
+            Ok(None) => std::process::exit(0),
+            Err(err) => {
+                eprintln!("error: {}\n\n{}", err, USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    /// Parse the given arguments, returns None when the program should exit
+    /// after printing the help or version
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> AppResult<Option<Self>> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Accept both `--port 6600` and `--port=6600`
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+                _ => (arg.clone(), None),
+            };
+            let mut value = || -> AppResult<String> {
+                match inline {
+                    Some(value) => Ok(value.to_string()),
+                    None => args
+                        .next()
+                        .ok_or_else(|| format!("{} needs a value", flag).into()),
+                }
+            };
+
+            match flag.as_str() {
+                "-h" | "--host" => parsed.host = Some(value()?),
+                "-p" | "--port" => parsed.port = Some(value()?),
+                "-P" | "--password" => parsed.password = Some(value()?),
+                "-s" | "--socket" => parsed.socket = Some(value()?),
+                "-t" | "--tab" => parsed.tab = Some(value()?.parse()?),
+                "-c" | "--config" => parsed.config = Some(PathBuf::from(value()?)),
+                "-V" | "--version" => {
+                    println!("rmptui {}", env!("CARGO_PKG_VERSION"));
+                    return Ok(None);
+                }
+                "--help" => {
+                    println!("{}", USAGE);
+                    return Ok(None);
+                }
+                _ => return Err(format!("unexpected argument \"{}\"", arg).into()),
+            }
+        }
+
+        Ok(Some(parsed))
+    }
+
+    /// Work out the MPD address, options given here take precedence over
+    /// MPD_HOST and MPD_PORT
+    pub fn address(&self) -> AppResult<MpdAddress> {
+        let mut address = match &self.socket {
+            Some(socket) => MpdAddress {
+                endpoint: match socket.strip_prefix('@') {
+                    Some(name) => Endpoint::Abstract(name.to_string()),
+                    None => Endpoint::Unix(PathBuf::from(socket)),
+                },
+                password: None,
+            },
+            None => MpdAddress::resolve(self.host.as_deref(), self.port.as_deref())?,
+        };
+
+        if self.password.is_some() {
+            address.password.clone_from(&self.password);
+        }
+
+        Ok(address)
+    }
+}
@@ -0,0 +1,220 @@
+use crate::app::App;
+use crate::theme::Theme;
+use mpd::Song;
+use ratatui::layout::{Alignment, Constraint};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq)]
+/// Piece of a column format string
+enum Segment {
+    Text(String),
+    /// `{Artist}`, or `{Title|filename}` to fall back on the next field when empty
+    Field(Vec<String>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "ColumnConfig")]
+/// Column of the queue, directory browser and library tables
+pub struct Column {
+    pub header: String,
+    format: Vec<Segment>,
+    pub width: Constraint,
+    pub alignment: Alignment,
+    /// Name of the theme style the cells are drawn with
+    pub style: String,
+}
+
+impl Column {
+    /// Text of the cell for the song
+    pub fn format(&self, song: &Song) -> String {
+        let mut cell = String::new();
+        for segment in &self.format {
+            match segment {
+                Segment::Text(text) => cell.push_str(text),
+                Segment::Field(fields) => {
+                    if let Some(value) = fields
+                        .iter()
+                        .filter_map(|field| field_value(song, field))
+                        .find(|value| !value.is_empty())
+                    {
+                        cell.push_str(&value);
+                    }
+                }
+            }
+        }
+        cell
+    }
+}
+
+/// Artist, Track, Title, Album and Time
+pub fn default_columns() -> Vec<Column> {
+    [
+        ("Artist", "{Artist}", "34%", "left", "artist"),
+        ("Track", "{Track}", "3%", "left", "track"),
+        ("Title", "{Title|file}", "30+", "left", "title"),
+        ("Album", "{Album}", "30%", "left", "album"),
+        ("Time", "{Time}", "3%", "left", "time"),
+    ]
+    .into_iter()
+    .map(|(header, format, width, align, style)| {
+        Column::try_from(ColumnConfig {
+            header: Some(header.to_string()),
+            tag: None,
+            format: Some(format.to_string()),
+            width: width.to_string(),
+            align: align.to_string(),
+            style: style.to_string(),
+        })
+        .expect("invalid default column")
+    })
+    .collect()
+}
+
+/// Value of a tag, or of one of the fields MPD sends with every song
+fn field_value(song: &Song, field: &str) -> Option<String> {
+    match field.to_lowercase().as_str() {
+        "file" => Some(song.file.clone()),
+        "filename" => song.file.rsplit('/').next().map(str::to_string),
+        "title" => song.title.clone(),
+        "artist" => song.artist.clone(),
+        "name" => song.name.clone(),
+        "last-modified" => song.last_mod.clone(),
+        "time" => song.duration.map(App::format_time),
+        _ => {
+            let values = song
+                .tags
+                .iter()
+                .filter(|(tag, _)| tag.eq_ignore_ascii_case(field))
+                .map(|(_, value)| value.as_str())
+                .collect::<Vec<&str>>();
+            if values.is_empty() {
+                None
+            } else {
+                Some(values.join(", "))
+            }
+        }
+    }
+}
+
+/// Split `{Disc}/{Track}` into text and fields
+fn parse_format(format: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut rest = format;
+
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            segments.push(Segment::Text(rest[..start].to_string()));
+        }
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed \"{{\" in column format \"{}\"", format))?;
+        let fields = rest[start + 1..start + end]
+            .split('|')
+            .map(|field| field.trim().to_string())
+            .collect::<Vec<String>>();
+        if fields.iter().any(String::is_empty) {
+            return Err(format!("empty field in column format \"{}\"", format));
+        }
+        segments.push(Segment::Field(fields));
+        rest = &rest[start + end + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest.to_string()));
+    }
+
+    Ok(segments)
+}
+
+/// `34%` of the table, `20` cells, or at least `20+` cells
+fn parse_width(width: &str) -> Result<Constraint, String> {
+    let invalid = || {
+        format!(
+            "invalid column width \"{}\", expected 30%, 30 or 30+",
+            width
+        )
+    };
+    if let Some(percent) = width.strip_suffix('%') {
+        match percent.trim().parse() {
+            Ok(percent) if percent <= 100 => Ok(Constraint::Percentage(percent)),
+            _ => Err(invalid()),
+        }
+    } else if let Some(min) = width.strip_suffix('+') {
+        Ok(Constraint::Min(min.trim().parse().map_err(|_| invalid())?))
+    } else {
+        Ok(Constraint::Length(
+            width.trim().parse().map_err(|_| invalid())?,
+        ))
+    }
+}
+
+/// `[[columns]]` entry of the configuration file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColumnConfig {
+    /// Defaults to the tag
+    header: Option<String>,
+    /// Shorthand for a format showing only this tag
+    tag: Option<String>,
+    format: Option<String>,
+    #[serde(default = "default_width")]
+    width: String,
+    #[serde(default = "default_align")]
+    align: String,
+    #[serde(default = "default_style")]
+    style: String,
+}
+
+fn default_width() -> String {
+    "10+".to_string()
+}
+
+fn default_align() -> String {
+    "left".to_string()
+}
+
+fn default_style() -> String {
+    "title".to_string()
+}
+
+impl TryFrom<ColumnConfig> for Column {
+    type Error = String;
+
+    fn try_from(config: ColumnConfig) -> Result<Self, Self::Error> {
+        let format = match (&config.tag, &config.format) {
+            (Some(_), Some(_)) => return Err("a column can not have both tag and format".into()),
+            (Some(tag), None) => vec![Segment::Field(vec![tag.clone()])],
+            (None, Some(format)) => parse_format(format)?,
+            (None, None) => return Err("a column needs either a tag or a format".into()),
+        };
+
+        let header = match (config.header, config.tag) {
+            (Some(header), _) => header,
+            (None, Some(tag)) => tag,
+            (None, None) => String::new(),
+        };
+
+        let alignment = match config.align.as_str() {
+            "left" => Alignment::Left,
+            "center" => Alignment::Center,
+            "right" => Alignment::Right,
+            align => {
+                return Err(format!(
+                    "invalid column alignment \"{}\", expected left, center or right",
+                    align
+                ))
+            }
+        };
+
+        if Theme::default().style(&config.style).is_none() {
+            return Err(format!("unknown theme style \"{}\"", config.style));
+        }
+
+        Ok(Column {
+            header,
+            format,
+            width: parse_width(&config.width)?,
+            alignment,
+            style: config.style,
+        })
+    }
+}
@@ -0,0 +1,235 @@
+use crate::app::{AppResult, SelectedTab};
+use crate::columns::{default_columns, Column};
+use crate::event_handler::keymap::Keymap;
+use crate::theme::Theme;
+use serde::{de, Deserialize, Deserializer};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extensions of the files shown in the directory browser and added to the queue
+pub const DEFAULT_AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "ogg", "flac", "m4a", "wav", "aac", "opus", "ape", "wma", "mpc", "aiff", "dff", "mp2",
+    "mka",
+];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+/// Settings read from `~/.config/rmptui/config.toml`
+pub struct Config {
+    /// Milliseconds between two ticks, which redraw the progress bar
+    pub tick_rate: u64,
+    /// Seconds skipped when seeking forward or backward
+    pub seek_step: u64,
+    /// Percents added or removed when changing the volume
+    pub volume_step: i8,
+    /// Files with other extensions are hidden in the directory browser
+    pub audio_extensions: Vec<String>,
+    /// Tab shown on startup
+    #[serde(deserialize_with = "deserialize_tab")]
+    pub default_tab: SelectedTab,
+    /// What Enter on a song and the dmenu prompt do with it
+    pub add_mode: AddMode,
+    /// Key bindings, `[keys.<context>]` tables merged over the defaults
+    pub keys: Keymap,
+    /// Colors, a preset and the styles which differ from it
+    pub theme: Theme,
+    /// Columns of the queue, directory browser and library, `[[columns]]` entries
+    pub columns: Vec<Column>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            tick_rate: 1000,
+            seek_step: 2,
+            volume_step: 2,
+            audio_extensions: DEFAULT_AUDIO_EXTENSIONS
+                .iter()
+                .map(|ext| ext.to_string())
+                .collect(),
+            default_tab: SelectedTab::Queue,
+            add_mode: AddMode::Play,
+            keys: Keymap::default(),
+            theme: Theme::default(),
+            columns: default_columns(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the configuration from the given file, or from the default location
+    ///
+    /// A missing file at the default location is not an error, the defaults are
+    /// used instead. Colors are turned off when NO_COLOR is set.
+    pub fn load(path: Option<&Path>) -> AppResult<Self> {
+        let mut config = Self::read(path)?;
+        if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            config.theme = config.theme.without_colors();
+        }
+        Ok(config)
+    }
+
+    fn read(path: Option<&Path>) -> AppResult<Self> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match Self::default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if !required && err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Config::default())
+            }
+            Err(err) => return Err(format!("{}: {}", path.display(), err).into()),
+        };
+
+        Self::parse(&contents).map_err(|err| format!("{}: {}", path.display(), err).into())
+    }
+
+    /// Parse and validate the contents of a configuration file
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut config: Config = toml::from_str(contents).map_err(|err| err.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// `$XDG_CONFIG_HOME/rmptui/config.toml`, or `~/.config/rmptui/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(dir.join("rmptui").join("config.toml"))
+    }
+
+    /// Check the values are usable, and normalize the extensions
+    fn validate(&mut self) -> Result<(), String> {
+        if !(10..=60_000).contains(&self.tick_rate) {
+            return Err(format!(
+                "tick_rate must be between 10 and 60000 milliseconds, got {}",
+                self.tick_rate
+            ));
+        }
+        if self.seek_step == 0 {
+            return Err("seek_step must be at least 1 second".to_string());
+        }
+        if !(1..=100).contains(&self.volume_step) {
+            return Err(format!(
+                "volume_step must be between 1 and 100, got {}",
+                self.volume_step
+            ));
+        }
+
+        for ext in self.audio_extensions.iter_mut() {
+            *ext = ext.trim_start_matches('.').to_lowercase();
+            if ext.is_empty() || ext.contains(['.', '/']) {
+                return Err(format!("invalid entry \"{}\" in audio_extensions", ext));
+            }
+        }
+        if self.audio_extensions.is_empty() {
+            return Err("audio_extensions must not be empty".to_string());
+        }
+        if self.columns.is_empty() {
+            return Err("columns must not be empty".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a song picked with Enter starts playing
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddMode {
+    /// Add the song and play it, or play it if already in the queue
+    Play,
+    /// Only add the song at the end of the queue, if not already there
+    Add,
+}
+
+fn deserialize_tab<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SelectedTab, D::Error> {
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(contents: &str) -> String {
+        Config::parse(contents).unwrap_err()
+    }
+
+    #[test]
+    fn empty_file_gives_the_defaults() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.tick_rate, 1000);
+        assert_eq!(config.seek_step, 2);
+        assert_eq!(config.default_tab, SelectedTab::Queue);
+        assert_eq!(config.add_mode, AddMode::Play);
+        assert_eq!(
+            config.audio_extensions.len(),
+            DEFAULT_AUDIO_EXTENSIONS.len()
+        );
+    }
+
+    #[test]
+    fn valid_config() {
+        let config = Config::parse(
+            r#"
+            tick_rate = 250
+            seek_step = 10
+            volume_step = 5
+            audio_extensions = [".MP3", "flac"]
+            default_tab = "Library"
+            add_mode = "add"
+
+            [keys.global]
+            q = "toggle_pause"
+
+            [[columns]]
+            tag = "Date"
+            width = "6"
+
+            [[columns]]
+            header = "Song"
+            format = "{Artist} - {Title|filename}"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.tick_rate, 250);
+        assert_eq!(config.seek_step, 10);
+        assert_eq!(config.volume_step, 5);
+        assert_eq!(config.audio_extensions, ["mp3", "flac"]);
+        assert_eq!(config.default_tab, SelectedTab::Library);
+        assert_eq!(config.add_mode, AddMode::Add);
+        assert_eq!(config.columns.len(), 2);
+    }
+
+    #[test]
+    fn unknown_keys() {
+        assert!(error("tickrate = 100").contains("tickrate"));
+        assert!(error("[[columns]]\ntag = \"Date\"\ncolour = \"red\"").contains("colour"));
+        assert!(error("default_tab = \"albums\"").contains("unknown tab"));
+        assert!(error("add_mode = \"queue\"").contains("queue"));
+    }
+
+    #[test]
+    fn validation_errors() {
+        assert!(error("tick_rate = 5").contains("tick_rate"));
+        assert!(error("tick_rate = 60001").contains("tick_rate"));
+        assert!(error("seek_step = 0").contains("seek_step"));
+        assert!(error("volume_step = 0").contains("volume_step"));
+        assert!(error("volume_step = 101").contains("volume_step"));
+        assert!(error("audio_extensions = []").contains("must not be empty"));
+        assert!(error("audio_extensions = [\".\"]").contains("invalid entry"));
+        assert!(error("audio_extensions = [\"tar.gz\"]").contains("invalid entry"));
+        assert!(error("audio_extensions = [\"a/b\"]").contains("invalid entry"));
+        assert!(error("columns = []").contains("columns must not be empty"));
+    }
+}
@@ -1,9 +1,49 @@
+use crate::address::{MpdAddress, MpdClient};
 use crate::app::AppResult;
-use crate::utils::is_installed;
+use crate::config::AddMode;
+use crate::utils::{dmenu, is_installed};
+use mpd::error::ErrorCode;
 use mpd::song::Song;
-use mpd::{Client, State};
-use simple_dmenu::dmenu;
-use std::time::Duration;
+use mpd::{Query, State, Term};
+use std::error::Error;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// How long the connection may stay silent before the status is refreshed
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Upper bound for the delay between two reconnection attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Delay before the given reconnection attempt, doubling each time up to [`MAX_BACKOFF`]
+pub fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs(1 << attempt.min(5)).min(MAX_BACKOFF)
+}
+
+/// Checks if an error means the connection to MPD is gone,
+/// as opposed to MPD refusing a single command
+pub fn is_connection_error(err: &(dyn Error + 'static)) -> bool {
+    if let Some(err) = err.downcast_ref::<mpd::error::Error>() {
+        matches!(err, mpd::error::Error::Io(_) | mpd::error::Error::Proto(_))
+    } else {
+        err.is::<io::Error>()
+    }
+}
+
+/// Describes an error in a way that fits in the status line
+pub fn describe_error(err: &(dyn Error + 'static)) -> String {
+    match err.downcast_ref::<mpd::error::Error>() {
+        Some(mpd::error::Error::Server(err)) => match err.code {
+            ErrorCode::Permission => format!(
+                "Permission denied for \"{}\", check the MPD password",
+                err.command
+            ),
+            ErrorCode::Password => "Incorrect MPD password".to_string(),
+            _ => format!("{}: {}", err.command, err.detail),
+        },
+        _ => err.to_string(),
+    }
+}
 
 /// Defines the current status of volume (Muted or UnMuted)
 #[derive(Debug)]
@@ -12,10 +52,26 @@ pub enum VolumeStatus {
     Unmuted,
 }
 
+/// Defines the state of the connection to the MPD server
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ConnectionState {
+    Connected,
+    /// Waiting for `retry_at` before the next attempt, after `attempt` failed ones
+    Reconnecting {
+        attempt: u32,
+        retry_at: Instant,
+    },
+    /// The connection was just lost and no retry is scheduled yet
+    Disconnected,
+}
+
 #[derive(Debug)]
 /// struct storing the mpd Client related stuff
 pub struct Connection {
-    pub conn: Client,
+    client: Option<MpdClient>,
+    pub address: MpdAddress,
+    pub conn_state: ConnectionState,
+    pub last_error: Option<String>,
     pub songs_filenames: Vec<String>,
     pub state: String,
     pub elapsed: Duration,
@@ -23,57 +79,128 @@ pub struct Connection {
     pub current_song: Song,
     pub stats: mpd::Stats,
     pub status: mpd::Status,
+    pub status_time: Instant,
+    pub queue_version: u32,
     pub volume_status: VolumeStatus,
 }
 
 impl Connection {
     /// Create a new connection
-    pub fn builder(addrs: &str) -> AppResult<Self> {
-        let mut conn = Client::connect(addrs).unwrap_or_else(|_| {
-            eprintln!("Error connecting to mpd server, Make sure mpd is running");
-            std::process::exit(1);
-        });
-
-        let empty_song = Song {
-            file: "No Song playing or in Queue".to_string(),
-            ..Default::default()
+    ///
+    /// Failing to reach MPD is not an error, the connection just starts out
+    /// disconnected and [`Connection::reconnect`] keeps retrying.
+    pub fn builder(address: MpdAddress) -> AppResult<Self> {
+        let mut conn = Self {
+            client: None,
+            address,
+            conn_state: ConnectionState::Disconnected,
+            last_error: None,
+            songs_filenames: Vec::new(),
+            state: "Stopped".to_string(),
+            elapsed: Duration::default(),
+            total_duration: Duration::default(),
+            current_song: Song {
+                file: "No Song playing or in Queue".to_string(),
+                ..Default::default()
+            },
+            stats: mpd::Stats::default(),
+            status: mpd::Status::default(),
+            status_time: Instant::now(),
+            queue_version: 0,
+            volume_status: VolumeStatus::Unmuted,
         };
 
-        let songs_filenames: Vec<String> = conn.listall()?.into_iter().map(|x| x.file).collect();
+        if let Err(err) = conn.connect() {
+            conn.last_error = Some(describe_error(err.as_ref()));
+            conn.conn_state = ConnectionState::Reconnecting {
+                attempt: 1,
+                retry_at: Instant::now() + backoff(1),
+            };
+        }
 
-        let status = conn.status().unwrap();
-        let (elapsed, total) = status.time.unwrap_or_default();
-        let stats = conn.stats().unwrap_or_default();
+        Ok(conn)
+    }
 
-        let current_song = conn
-            .currentsong()
-            .unwrap_or_else(|_| Some(empty_song.clone()))
-            .unwrap_or(empty_song);
+    /// Open the connection to MPD and fetch the state which does not come from `status`
+    fn connect(&mut self) -> AppResult<()> {
+        let mut client = self.address.connect()?;
+        self.songs_filenames = client.listall()?.into_iter().map(|x| x.file).collect();
+        self.client = Some(client);
+        self.conn_state = ConnectionState::Connected;
+        self.last_error = None;
+        self.queue_version = 0;
+        self.update_status()?;
+        Ok(())
+    }
 
-        let volume_status = VolumeStatus::Unmuted;
+    /// Drop the connection, the next [`Connection::reconnect`] schedules a retry
+    pub fn disconnect(&mut self) {
+        if self.conn_state == ConnectionState::Connected {
+            self.client = None;
+            self.conn_state = ConnectionState::Disconnected;
+        }
+    }
 
-        Ok(Self {
-            conn,
-            songs_filenames,
-            state: "Stopped".to_string(),
-            elapsed,
-            total_duration: total,
-            current_song,
-            stats,
-            status,
-            volume_status,
+    /// Drive the reconnection state machine
+    ///
+    /// Returns true if the connection was re-established, so the caller can reload its views.
+    pub fn reconnect(&mut self) -> bool {
+        if self.conn_state == ConnectionState::Disconnected {
+            self.conn_state = ConnectionState::Reconnecting {
+                attempt: 0,
+                retry_at: Instant::now(),
+            };
+        }
+
+        match self.conn_state {
+            ConnectionState::Reconnecting { attempt, retry_at } if Instant::now() >= retry_at => {
+                if let Err(err) = self.connect() {
+                    self.last_error = Some(describe_error(err.as_ref()));
+                    self.client = None;
+                    self.conn_state = ConnectionState::Reconnecting {
+                        attempt: attempt + 1,
+                        retry_at: Instant::now() + backoff(attempt + 1),
+                    };
+                    false
+                } else {
+                    true
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Skip the remaining backoff, used once MPD is known to be reachable again
+    pub fn retry_now(&mut self) {
+        if let ConnectionState::Reconnecting { attempt, .. } = self.conn_state {
+            self.conn_state = ConnectionState::Reconnecting {
+                attempt,
+                retry_at: Instant::now(),
+            };
+        }
+    }
+
+    /// Get the mpd client, or an error if MPD is currently unreachable
+    pub fn client(&mut self) -> AppResult<&mut MpdClient> {
+        self.client.as_mut().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotConnected, "not connected to mpd").into()
         })
     }
 
     /// Dmenu prompt for selecting songs
-    pub fn play_dmenu(&mut self) -> AppResult<()> {
+    pub fn play_dmenu(&mut self, mode: AddMode) -> AppResult<()> {
         if is_installed("dmenu") {
             let ss: Vec<&str> = self.songs_filenames.iter().map(|x| x.as_str()).collect();
-            let op = dmenu!(iter &ss; args "-p", "Choose a song: ", "-l", "30");
+            let op = dmenu(&ss, &["-p", "Choose a song: ", "-l", "30"])?;
             let index = ss.iter().position(|s| s == &op);
             if let Some(i) = index {
                 let song = self.get_song_with_only_filename(ss.get(i).unwrap());
-                self.push(&song)?;
+                match mode {
+                    AddMode::Play => self.push(&song)?,
+                    AddMode::Add => {
+                        self.client()?.push(&song)?;
+                    }
+                }
             }
         }
 
@@ -81,20 +208,21 @@ impl Connection {
     }
 
     /// Update status
-    pub fn update_status(&mut self) {
-        let status = self.conn.status().unwrap();
-        let stats = self.conn.stats().unwrap_or_default();
-
+    pub fn update_status(&mut self) -> AppResult<()> {
         let empty_song = self.get_song_with_only_filename("No Song playing or in Queue");
 
-        let current_song = self
-            .conn
+        let client = self.client()?;
+        let status = client.status()?;
+        let stats = client.stats().unwrap_or_default();
+
+        let current_song = client
             .currentsong()
             .unwrap_or_else(|_| Some(empty_song.clone()))
             .unwrap_or(empty_song);
 
         // Status
         self.status = status.clone();
+        self.status_time = Instant::now();
 
         // Playback State
         self.state = match status.state {
@@ -113,6 +241,29 @@ impl Connection {
 
         // Stats
         self.stats = stats;
+        Ok(())
+    }
+
+    /// Advance the elapsed time locally while playing, so the progress bar moves
+    /// without asking MPD for its status every tick
+    pub fn tick(&mut self) -> AppResult<()> {
+        if self.conn_state != ConnectionState::Connected {
+            return Ok(());
+        }
+
+        // MPD drops clients which stay silent for longer than its connection_timeout,
+        // so resync the status every now and then to keep the connection alive
+        if self.status_time.elapsed() >= KEEPALIVE_INTERVAL {
+            self.update_status()?;
+        }
+
+        if self.status.state == State::Play {
+            let (elapsed, _) = self.status.time.unwrap_or_default();
+            let elapsed = self.status.elapsed.unwrap_or(elapsed) + self.status_time.elapsed();
+            self.elapsed = elapsed.min(self.total_duration);
+        }
+
+        Ok(())
     }
 
     /// Get progress ratio of current playing song
@@ -132,34 +283,91 @@ impl Connection {
 
     /// push the given song to queue
     pub fn push(&mut self, song: &Song) -> AppResult<()> {
-        if self.conn.queue().unwrap().is_empty() {
-            self.conn.push(song).unwrap();
-            self.conn.play().unwrap();
+        let client = self.client()?;
+        if client.queue()?.is_empty() {
+            client.push(song)?;
+            client.play()?;
         } else {
-            self.conn.push(song)?;
-            let len: u32 = (self.conn.queue().unwrap().len() - 1).try_into().unwrap();
-            self.conn.switch(len)?;
-            if self.conn.status()?.state == State::Stop {
-                self.conn.play()?;
+            client.push(song)?;
+            let len: u32 = (client.queue()?.len() - 1).try_into()?;
+            client.switch(len)?;
+            if client.status()?.state == State::Stop {
+                client.play()?;
             }
         }
 
         Ok(())
     }
 
+    /// Insert the songs right after the current one, in order, without changing
+    /// what is playing. They go to the end when no song is current
+    pub fn insert_next(&mut self, songs: &[Song]) -> AppResult<()> {
+        let client = self.client()?;
+        match client.status()?.song {
+            Some(current) => {
+                let pos = current.pos as usize + 1;
+                for (i, song) in songs.iter().enumerate() {
+                    client.insert(song, pos + i)?;
+                }
+            }
+            None => {
+                for song in songs {
+                    client.push(song)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the songs at the given positions of the queue right after the
+    /// current one, keeping their order
+    pub fn move_next(&mut self, positions: &[usize]) -> AppResult<()> {
+        let client = self.client()?;
+        let Some(current) = client.status()?.song else {
+            return Ok(());
+        };
+
+        // Follow the moves on a copy of the queue, as each one shifts the positions
+        let mut queue: Vec<usize> = (0..client.queue()?.len()).collect();
+        let current = current.pos as usize;
+        let mut moved = 0;
+        for pos in positions {
+            if *pos == current {
+                continue;
+            }
+            let Some(from) = queue.iter().position(|p| p == pos) else {
+                continue;
+            };
+            queue.remove(from);
+            let to = queue.iter().position(|p| *p == current).unwrap_or_default() + 1 + moved;
+            queue.insert(to, *pos);
+            client.shift(from as u32, to)?;
+            moved += 1;
+        }
+        Ok(())
+    }
+
     /// Push all songs of a playlist into queue
     pub fn load_playlist(&mut self, playlist: &str) -> AppResult<()> {
-        self.conn.load(playlist, ..)?;
-        self.conn.play()?;
+        let client = self.client()?;
+        client.load(playlist, ..)?;
+        client.play()?;
         Ok(())
     }
 
     /// Add given song to playlist
     pub fn add_to_playlist(&mut self, playlist: &str, song: &Song) -> AppResult<()> {
-        self.conn.pl_push(playlist, song)?;
+        self.client()?.pl_push(playlist, song)?;
         Ok(())
     }
 
+    /// Every song of the directory and its subdirectories, in a single request
+    pub fn songs_under(&mut self, directory: &str) -> AppResult<Vec<Song>> {
+        let mut query = Query::new();
+        query.and(Term::Base, directory);
+        Ok(self.client()?.find(&query, None)?)
+    }
+
     /// Given a filename, get instance of Song with only filename
     pub fn get_song_with_only_filename(&self, filename: &str) -> Song {
         Song {
@@ -193,43 +401,49 @@ impl Connection {
 
     // Playback controls
     /// Pause playback
-    pub fn pause(&mut self) {
-        self.conn.pause(true).unwrap();
+    pub fn pause(&mut self) -> AppResult<()> {
+        self.client()?.pause(true)?;
+        Ok(())
     }
 
     /// Toggles playback
-    pub fn toggle_pause(&mut self) {
-        self.conn.toggle_pause().unwrap();
+    pub fn toggle_pause(&mut self) -> AppResult<()> {
+        self.client()?.toggle_pause()?;
+        Ok(())
     }
 
     /// Toggle Repeat mode
-    pub fn toggle_repeat(&mut self) {
+    pub fn toggle_repeat(&mut self) -> AppResult<()> {
         let mode = self.status.repeat;
-        self.conn.repeat(!mode).unwrap();
+        self.client()?.repeat(!mode)?;
+        Ok(())
     }
 
     /// Toggle random mode
-    pub fn toggle_random(&mut self) {
+    pub fn toggle_random(&mut self) -> AppResult<()> {
         let mode = self.status.random;
-        self.conn.random(!mode).unwrap();
+        self.client()?.random(!mode)?;
+        Ok(())
     }
 
     // Volume controls
     /// Increase Volume
-    pub fn inc_volume(&mut self, v: i8) {
+    pub fn inc_volume(&mut self, v: i8) -> AppResult<()> {
         let cur = self.status.volume;
-        if cur + v <= 100 {
+        if cur < 100 {
+            self.client()?.volume(cur.saturating_add(v).min(100))?;
             self.volume_status = VolumeStatus::Unmuted;
-            self.conn.volume(cur + v).unwrap();
         }
+        Ok(())
     }
 
     /// Decrease volume
-    pub fn dec_volume(&mut self, v: i8) {
+    pub fn dec_volume(&mut self, v: i8) -> AppResult<()> {
         let cur = self.status.volume;
-        if cur - v >= 0 {
+        if cur > 0 {
+            self.client()?.volume(cur.saturating_sub(v).max(0))?;
             self.volume_status = VolumeStatus::Unmuted;
-            self.conn.volume(cur - v).unwrap();
         }
+        Ok(())
     }
 }
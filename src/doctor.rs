@@ -0,0 +1,104 @@
+use std::collections::{HashMap, HashSet};
+
+use mpd::{Query, Song, Term};
+
+use crate::{app::AppResult, connection::Connection};
+
+/// A song of a stored playlist which is not in the database anymore
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeadEntry {
+    pub playlist: String,
+    pub position: usize,
+    pub song: Song,
+    /// Where the song most likely went
+    pub suggestion: Option<String>,
+}
+
+/// The songs of the playlist missing from the database, streams aside
+pub fn dead_entries(playlist: &str, songs: &[Song], known: &HashSet<&str>) -> Vec<DeadEntry> {
+    songs
+        .iter()
+        .enumerate()
+        .filter(|(_, song)| !song.file.contains("://") && !known.contains(song.file.as_str()))
+        .map(|(position, song)| DeadEntry {
+            playlist: playlist.to_string(),
+            position,
+            song: song.clone(),
+            suggestion: None,
+        })
+        .collect()
+}
+
+/// Finds where the songs which left the database went
+pub struct Relocator<'a> {
+    by_name: HashMap<&'a str, Vec<&'a str>>, // Files of the database, by file name
+    by_stem: HashMap<String, Vec<&'a str>>,  // Same, by lowercase file name without extension
+}
+
+impl<'a> Relocator<'a> {
+    pub fn new(files: &'a [String]) -> Self {
+        let mut by_name: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut by_stem: HashMap<String, Vec<&str>> = HashMap::new();
+        for file in files {
+            let name = file_name(file);
+            by_name.entry(name).or_default().push(file);
+            by_stem.entry(stem(name).to_lowercase()).or_default().push(file);
+        }
+        Self { by_name, by_stem }
+    }
+
+    /// New place of a missing file: the song with the artist and title read
+    /// from an "Artist - Title" file name, else the file with the same name,
+    /// else the same name with another extension. Among several, the one
+    /// sharing the most directories wins.
+    pub fn relocate(&self, song: &Song, conn: &mut Connection) -> AppResult<Option<String>> {
+        // Songs gone from the database have no tags, only the file name is left
+        if let Some((artist, title)) = artist_and_title(file_name(&song.file)) {
+            let mut query = Query::new();
+            query
+                .and(Term::Tag("Artist".into()), artist)
+                .and(Term::Tag("Title".into()), title);
+            let found = conn.client()?.find(&query, None)?;
+            let files: Vec<&str> = found.iter().map(|s| s.file.as_str()).collect();
+            if let Some(file) = closest(&song.file, &files) {
+                return Ok(Some(file.to_string()));
+            }
+        }
+
+        let name = file_name(&song.file);
+        let candidates = self
+            .by_name
+            .get(name)
+            .or_else(|| self.by_stem.get(&stem(name).to_lowercase()));
+        Ok(candidates
+            .and_then(|files| closest(&song.file, files))
+            .map(str::to_string))
+    }
+}
+
+fn file_name(file: &str) -> &str {
+    file.rsplit('/').next().unwrap_or(file)
+}
+
+fn stem(name: &str) -> &str {
+    match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => name,
+    }
+}
+
+/// Artist and title of a file named "Artist - Title.ext"
+fn artist_and_title(name: &str) -> Option<(&str, &str)> {
+    let (artist, title) = stem(name).split_once(" - ")?;
+    let (artist, title) = (artist.trim(), title.trim());
+    (!artist.is_empty() && !title.is_empty()).then_some((artist, title))
+}
+
+/// The candidate with the most directories in common with the file
+fn closest<'b>(file: &str, candidates: &[&'b str]) -> Option<&'b str> {
+    let dirs: HashSet<&str> = file.split('/').collect();
+    candidates
+        .iter()
+        .max_by_key(|candidate| candidate.split('/').filter(|d| dirs.contains(d)).count())
+        .copied()
+}
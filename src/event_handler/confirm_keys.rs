@@ -0,0 +1,18 @@
+use crate::{
+    app::{App, AppResult},
+    event_handler::keymap::{Action, Context},
+};
+use crossterm::event::{KeyCode, KeyEvent};
+
+pub fn handle_confirm_keys(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    // Enter says yes, any key which is not an answer says no
+    let answer = match (
+        app.config.keys.get(Context::Input, key_event.into()),
+        key_event.code,
+    ) {
+        (Some(Action::Confirm), _) => 'y',
+        (_, KeyCode::Char(c)) => c.to_ascii_lowercase(),
+        _ => 'n',
+    };
+    app.answer_confirmation(answer)
+}
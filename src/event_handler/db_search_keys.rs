@@ -0,0 +1,41 @@
+use crate::{
+    app::{App, AppResult},
+    event_handler::keymap::{Action, Context},
+    ui::InputMode,
+};
+use crossterm::event::{KeyCode, KeyEvent};
+
+pub fn handle_db_search_keys(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    match app.config.keys.get(Context::Input, key_event.into()) {
+        Some(Action::Cancel) => {
+            app.db_search_input.clear();
+            app.reset_cursor();
+            app.inputmode = InputMode::Normal;
+        }
+        Some(Action::Confirm) => {
+            app.reset_cursor();
+            app.inputmode = InputMode::Normal;
+            app.run_database_search()?;
+            app.db_search_input.clear();
+        }
+
+        Some(Action::DeleteChar) => {
+            app.delete_char();
+        }
+
+        Some(Action::CursorLeft) => {
+            app.move_cursor_left();
+        }
+
+        Some(Action::CursorRight) => {
+            app.move_cursor_right();
+        }
+
+        _ => {
+            if let KeyCode::Char(to_insert) = key_event.code {
+                app.enter_char(to_insert);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use crate::app::{App, AppResult, Confirmation};
+use crate::event_handler::keymap::Action;
+
+pub fn handle_doctor_keys(action: Action, app: &mut App) -> AppResult<()> {
+    match action {
+        Action::Close => app.doctor_popup = false,
+
+        Action::Down => app.dead_entries.next(),
+        Action::Up => app.dead_entries.prev(),
+        Action::Top => app.dead_entries.index = 0,
+        Action::Bottom => app.dead_entries.index = app.dead_entries.list.len().saturating_sub(1),
+
+        // Put the highlighted song where it likely went
+        Action::FixEntry => {
+            if let Some(entry) = app.dead_entries.list.get(app.dead_entries.index) {
+                app.heal_playlists(vec![entry.clone()], false)?;
+            }
+        }
+        Action::FixAll => {
+            let entries = app.dead_entries.list.clone();
+            app.heal_playlists(entries, false)?;
+        }
+
+        // Remove the highlighted song from its playlist
+        Action::PruneEntry => {
+            if let Some(entry) = app.dead_entries.list.get(app.dead_entries.index) {
+                app.heal_playlists(vec![entry.clone()], true)?;
+            }
+        }
+        Action::PruneAll if !app.dead_entries.list.is_empty() => {
+            let count = app.dead_entries.list.len();
+            app.ask_confirmation(Confirmation::PruneDeadEntries(count));
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
@@ -1,5 +1,8 @@
+use crate::address::MpdAddress;
 use crate::app::AppResult;
+use crate::connection::backoff;
 use crossterm::event::{self, Event as CrosstermEvent, KeyEvent, MouseEvent};
+use mpd::{Idle, Subsystem};
 use std::sync::mpsc;
 use std::thread;
 use std::time::{Duration, Instant};
@@ -15,8 +18,25 @@ pub enum Event {
     Mouse(MouseEvent),
     /// Terminal resize.
     Resize(u16, u16),
+    /// Change reported by MPD through `idle`.
+    Mpd(Subsystem),
+    /// The idle connection to MPD was established.
+    MpdConnected,
+    /// The idle connection to MPD was lost.
+    MpdDisconnected,
 }
 
+/// MPD subsystems the idle connection listens to.
+const IDLE_SUBSYSTEMS: [Subsystem; 7] = [
+    Subsystem::Player,
+    Subsystem::Queue,
+    Subsystem::Playlist,
+    Subsystem::Database,
+    Subsystem::Mixer,
+    Subsystem::Options,
+    Subsystem::Output,
+];
+
 /// Terminal event handler.
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -27,6 +47,8 @@ pub struct EventHandler {
     receiver: mpsc::Receiver<Event>,
     /// Event handler thread.
     handler: thread::JoinHandle<()>,
+    /// MPD idle thread.
+    idle_handler: Option<thread::JoinHandle<()>>,
 }
 
 impl EventHandler {
@@ -66,9 +88,47 @@ impl EventHandler {
             sender,
             receiver,
             handler,
+            idle_handler: None,
         }
     }
 
+    /// Spawns a thread holding a dedicated MPD connection in `idle` mode.
+    ///
+    /// Every subsystem change reported by MPD is forwarded as an [`Event::Mpd`],
+    /// so the application only refreshes its state when something actually changed.
+    /// The thread reconnects on its own, reporting the connection state through
+    /// [`Event::MpdConnected`] and [`Event::MpdDisconnected`].
+    pub fn watch_mpd(&mut self, address: &MpdAddress) {
+        let address = address.clone();
+        let sender = self.sender.clone();
+        self.idle_handler = Some(thread::spawn(move || {
+            let mut attempt = 0;
+            loop {
+                if let Ok(mut client) = address.connect() {
+                    attempt = 0;
+                    if sender.send(Event::MpdConnected).is_err() {
+                        return;
+                    }
+
+                    while let Ok(subsystems) = client.wait(&IDLE_SUBSYSTEMS) {
+                        for subsystem in subsystems {
+                            if sender.send(Event::Mpd(subsystem)).is_err() {
+                                return;
+                            }
+                        }
+                    }
+
+                    if sender.send(Event::MpdDisconnected).is_err() {
+                        return;
+                    }
+                }
+
+                attempt += 1;
+                thread::sleep(backoff(attempt));
+            }
+        }));
+    }
+
     /// Receive the next event from the handler thread.
     ///
     /// This function will always block the current thread if
@@ -0,0 +1,46 @@
+use crate::{
+    app::{App, AppResult},
+    event_handler::keymap::{Action, Context},
+    ui::InputMode,
+};
+use crossterm::event::{KeyCode, KeyEvent};
+
+pub fn handle_filter_keys(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    match app.config.keys.get(Context::Input, key_event.into()) {
+        // Show the whole list again
+        Some(Action::Cancel) => {
+            app.filter_input.clear();
+            app.update_filter();
+            app.reset_cursor();
+            app.inputmode = InputMode::Normal;
+        }
+
+        // Keep the list filtered
+        Some(Action::Confirm) => {
+            app.filter_input.clear();
+            app.reset_cursor();
+            app.inputmode = InputMode::Normal;
+        }
+
+        Some(Action::DeleteChar) => {
+            app.delete_char();
+            app.update_filter();
+        }
+
+        Some(Action::CursorLeft) => {
+            app.move_cursor_left();
+        }
+
+        Some(Action::CursorRight) => {
+            app.move_cursor_right();
+        }
+
+        _ => {
+            if let KeyCode::Char(to_insert) = key_event.code {
+                app.enter_char(to_insert);
+                app.update_filter();
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,14 +1,24 @@
 use crate::{
-    app::{App, AppResult, SelectedTab},
+    adder::{Destination, Source},
+    app::{App, AppResult, Confirmation, PlaylistPrompt, SelectedTab},
     connection::VolumeStatus,
+    library::LibraryLevel,
+    list::ListFilter,
     ui::InputMode,
 };
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use crossterm::event::{KeyEvent, MouseEvent, MouseEventKind};
 use std::time::Duration;
 
-use super::{new_pl_keys, pl_append_keys, pl_rename_keys, search_keys};
+use super::keymap::{Action, Context, Key, Lookup};
+use super::{
+    confirm_keys, db_search_keys, doctor_keys, filter_keys, new_pl_keys, pl_append_keys,
+    pl_content_keys, pl_rename_keys, search_keys,
+};
 
 pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    // Any key press dismisses the last error
+    app.error_message = None;
+
     // searching, playlist renaming, playlist appending
     if app.inputmode == InputMode::Editing {
         search_keys::handle_search_keys(key_event, app)?;
@@ -16,305 +26,514 @@ pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
         pl_rename_keys::handle_pl_rename_keys(key_event, app)?;
     } else if app.inputmode == InputMode::NewPlaylist {
         new_pl_keys::handle_new_pl_keys(key_event, app)?;
+    } else if app.inputmode == InputMode::DatabaseSearch {
+        db_search_keys::handle_db_search_keys(key_event, app)?;
+    } else if app.inputmode == InputMode::Filter {
+        filter_keys::handle_filter_keys(key_event, app)?;
+    } else if app.inputmode == InputMode::Confirm {
+        confirm_keys::handle_confirm_keys(key_event, app)?;
+    } else if app.doctor_popup {
+        if let Some(action) = resolve_key(key_event, app, &[Context::Doctor]) {
+            doctor_keys::handle_doctor_keys(action, app)?;
+        }
     } else if app.playlist_popup {
-        pl_append_keys::hande_pl_append_keys(key_event, app)?;
+        if let Some(action) = resolve_key(key_event, app, &[Context::Popup]) {
+            pl_append_keys::hande_pl_append_keys(action, app)?;
+        }
+    } else if app.selected_tab == SelectedTab::Playlists && app.pl_content_focus {
+        let contexts = [Context::PlaylistContent, Context::Global];
+        if let Some(action) = resolve_key(key_event, app, &contexts) {
+            pl_content_keys::handle_pl_content_keys(action, app)?;
+        }
     } else {
-        // General KeyMaps
-        match key_event.code {
-            // Quit
-            KeyCode::Char('q') => app.quit(),
-            KeyCode::Char('c') | KeyCode::Char('C') => {
-                if key_event.modifiers == KeyModifiers::CONTROL {
-                    app.quit();
-                } else {
-                    app.conn.conn.clear()?;
-                    app.conn.update_status();
-                    app.queue_list.list.clear();
-                    app.queue_list.reset_index();
-                }
+        // Keys bound in the current tab take precedence over the global ones
+        let tab = match app.selected_tab {
+            SelectedTab::Queue => Context::Queue,
+            SelectedTab::DirectoryBrowser => Context::DirectoryBrowser,
+            SelectedTab::Library => Context::Library,
+            SelectedTab::Search => Context::Search,
+            SelectedTab::Playlists => Context::Playlists,
+        };
+        if let Some(action) = resolve_key(key_event, app, &[tab, Context::Global]) {
+            let tab = app.selected_tab.clone();
+            handle_action(action, app)?;
+
+            // The selection belongs to the tab it was made in
+            if app.selected_tab != tab {
+                app.selection.clear();
             }
+        }
+    }
 
-            // Playback controls
-            // Toggle Pause
-            KeyCode::Char('p') => {
-                app.conn.toggle_pause();
-                app.conn.update_status();
-            }
+    // Drawing only reads the cache, the playlist which came into view is fetched now
+    app.cache_highlighted_playlist()
+}
 
-            // Pause
-            KeyCode::Char('s') => {
-                app.conn.pause();
-                app.conn.update_status();
+/// Add the key to the ones pressed so far, returns the action once they form
+/// a whole sequence
+fn resolve_key(key_event: KeyEvent, app: &mut App, contexts: &[Context]) -> Option<Action> {
+    app.pending_keys.push(Key::from(key_event));
+    match app.config.keys.lookup(contexts, &app.pending_keys) {
+        Lookup::Action(action) => {
+            app.pending_keys.clear();
+            Some(action)
+        }
+        Lookup::Pending => None,
+        Lookup::NotFound => {
+            // The sequence went nowhere, try again with the last key on its own
+            let retry = app.pending_keys.len() > 1;
+            app.pending_keys.clear();
+            if retry {
+                resolve_key(key_event, app, contexts)
+            } else {
+                None
             }
+        }
+    }
+}
 
-            // Toggle rpeat
-            KeyCode::Char('r') => {
-                app.conn.toggle_repeat();
-                app.conn.update_status();
-            }
+/// Run an action bound in one of the tabs, or globally
+pub fn handle_action(action: Action, app: &mut App) -> AppResult<()> {
+    match action {
+        Action::Quit => app.quit(),
 
-            // Toggle random
-            KeyCode::Char('z') => {
-                app.conn.toggle_random();
-                app.conn.update_status();
-            }
+        Action::ClearQueue => {
+            app.conn.client()?.clear()?;
+            app.conn.update_status()?;
+            app.queue_list.list.clear();
+            app.queue_list.reset_index();
+        }
 
-            // Dmenu prompt
-            KeyCode::Char('D') => {
-                app.conn.play_dmenu()?;
-                app.conn.update_status();
-            }
+        // Playback controls
+        Action::TogglePause => {
+            app.conn.toggle_pause()?;
+            app.conn.update_status()?;
+        }
+
+        Action::Pause => {
+            app.conn.pause()?;
+            app.conn.update_status()?;
+        }
+
+        Action::ToggleRepeat => {
+            app.conn.toggle_repeat()?;
+            app.conn.update_status()?;
+        }
+
+        Action::ToggleRandom => {
+            app.conn.toggle_random()?;
+            app.conn.update_status()?;
+        }
+
+        Action::Dmenu => {
+            app.conn.play_dmenu(app.config.add_mode)?;
+            app.conn.update_status()?;
+        }
 
-            // add to queue
-            KeyCode::Char('a') => app.playlist_popup = true,
-
-            // Fast forward
-            KeyCode::Char('f') => {
-                if !app.queue_list.list.is_empty() {
-                    let status = app.conn.conn.status().unwrap_or_default();
-                    let place = status.song.unwrap_or_default().pos;
-                    let (pos, _) = status.time.unwrap_or_default();
-                    let pos = Duration::from_secs(pos.as_secs().wrapping_add(2));
-                    app.conn.conn.seek(place, pos)?;
-                    app.conn.update_status();
+        // add to queue
+        Action::AddToPlaylist => app.playlist_popup = true,
+
+        Action::SeekForward if !app.queue_list.list.is_empty() => {
+            let status = app.conn.client()?.status().unwrap_or_default();
+            let place = status.song.unwrap_or_default().pos;
+            let (pos, _) = status.time.unwrap_or_default();
+            let pos = Duration::from_secs(pos.as_secs().saturating_add(app.config.seek_step));
+            app.conn.client()?.seek(place, pos)?;
+            app.conn.update_status()?;
+        }
+
+        Action::SeekBackward if !app.queue_list.list.is_empty() => {
+            let status = app.conn.client()?.status().unwrap_or_default();
+            let place = status.song.unwrap_or_default().pos;
+            let (pos, _) = status.time.unwrap_or_default();
+            let pos = Duration::from_secs(pos.as_secs().saturating_sub(app.config.seek_step));
+            app.conn.client()?.seek(place, pos)?;
+            app.conn.update_status()?;
+        }
+
+        // Tabs
+        Action::NextTab => app.cycle_tabls(),
+        Action::QueueTab => app.selected_tab = SelectedTab::Queue,
+        Action::BrowserTab => app.selected_tab = SelectedTab::DirectoryBrowser,
+        Action::LibraryTab => app.selected_tab = SelectedTab::Library,
+        Action::SearchTab => app.selected_tab = SelectedTab::Search,
+        Action::PlaylistsTab => app.selected_tab = SelectedTab::Playlists,
+
+        Action::NextSong if !app.queue_list.list.is_empty() => {
+            app.conn.client()?.next()?;
+            app.update_queue()?;
+            app.conn.update_status()?;
+        }
+
+        Action::PreviousSong if !app.queue_list.list.is_empty() => {
+            app.conn.client()?.prev()?;
+            app.update_queue()?;
+            app.conn.update_status()?;
+        }
+
+        // Volume controls
+        Action::VolumeUp => {
+            app.conn.inc_volume(app.config.volume_step)?;
+            app.conn.update_status()?;
+        }
+
+        Action::VolumeDown => {
+            app.conn.dec_volume(app.config.volume_step)?;
+            app.conn.update_status()?;
+        }
+
+        Action::ToggleMute => {
+            match app.conn.volume_status {
+                VolumeStatus::Muted(v) => {
+                    app.conn.client()?.volume(v)?;
+                    app.conn.volume_status = VolumeStatus::Unmuted;
+                }
+                VolumeStatus::Unmuted => {
+                    let current_volume = app.conn.status.volume;
+                    app.conn.client()?.volume(0)?;
+                    app.conn.volume_status = VolumeStatus::Muted(current_volume);
                 }
             }
+            app.conn.update_status()?;
+        }
 
-            // backward
-            KeyCode::Char('b') => {
-                if !app.queue_list.list.is_empty() {
-                    let status = app.conn.conn.status().unwrap_or_default();
-                    let place = status.song.unwrap_or_default().pos;
-                    let (pos, _) = status.time.unwrap_or_default();
-                    let pos = Duration::from_secs(pos.as_secs().wrapping_sub(2));
-                    app.conn.conn.seek(place, pos)?;
-                    app.conn.update_status();
-                }
+        Action::UpdateDatabase => {
+            app.conn.client()?.rescan()?;
+        }
+
+        Action::Search => app.inputmode = InputMode::Editing,
+        Action::NextMatch => app.jump_to_match(true),
+        Action::PreviousMatch => app.jump_to_match(false),
+
+        // Search the whole database, starting from the last query
+        Action::DatabaseSearch => {
+            app.db_search_input.clone_from(&app.db_search_query);
+            app.db_search_cursor_pos = app.db_search_input.chars().count();
+            app.inputmode = InputMode::DatabaseSearch;
+        }
+
+        // Add the selection to the queue, or remove it from the queue
+        Action::ToggleInQueue if !app.selection.is_empty() => app.add_selection_to_queue()?,
+
+        // Add or Remove from Current Playlist
+        Action::ToggleInQueue => {
+            app.handle_add_or_remove_from_current_playlist()?;
+            app.refresh_filter(app.selected_tab.clone());
+        }
+
+        // Narrow the list of the current tab, starting from its current filter
+        Action::Filter => {
+            app.filter_input = app
+                .filter()
+                .map(|filter| filter.query.clone())
+                .unwrap_or_default();
+            app.filter_cursor_pos = app.filter_input.chars().count();
+            app.inputmode = InputMode::Filter;
+        }
+
+        // Leave the selection first
+        Action::ClearFilter if !app.selection.is_empty() => app.selection.clear(),
+
+        // Also stops highlighting the matches of the last search
+        Action::ClearFilter => {
+            app.last_search.clear();
+            let tab = app.selected_tab.clone();
+            if let Some(filter) = app.filter_mut(&tab) {
+                *filter = None;
             }
+        }
 
-            // Cycle through tabs
-            KeyCode::Tab => {
-                app.cycle_tabls();
+        // Mark the highlighted row and go to the next one
+        Action::ToggleMark => {
+            let searchable = app.searchable(&app.selected_tab);
+            if searchable.row_count() != 0 {
+                let cursor = searchable.selected();
+                app.selection.toggle(cursor);
+                handle_action(Action::Down, app)?;
             }
+        }
+
+        // Append the selection to the queue, whatever add_mode says
+        Action::Add => app.add_selection(false)?,
+        Action::AddAndPlay => app.add_selection(true)?,
+
+        // Insert the selection after the current song, or move it there in the queue
+        Action::PlayNext => app.play_next()?,
 
-            // Directory browser tab
-            KeyCode::Char('1') => {
-                app.selected_tab = SelectedTab::Queue;
+        Action::VisualMode => {
+            let searchable = app.searchable(&app.selected_tab);
+            if searchable.row_count() != 0 {
+                let cursor = searchable.selected();
+                app.selection.toggle_visual(cursor);
             }
+        }
+
+        // Moving around the rows left by the filter
+        Action::Down if app.filter().is_some() => app.select_filtered(ListFilter::next),
+        Action::Up if app.filter().is_some() => app.select_filtered(ListFilter::prev),
+        Action::Top if app.filter().is_some() => {
+            app.select_filtered(|filter, _| filter.visible.first().copied())
+        }
+        Action::Bottom if app.filter().is_some() => {
+            app.select_filtered(|filter, _| filter.visible.last().copied())
+        }
 
-            // Playing queue tab
-            KeyCode::Char('2') => {
-                app.selected_tab = SelectedTab::DirectoryBrowser;
+        // Moving around the list of the current tab
+        Action::Down => match app.selected_tab {
+            SelectedTab::Queue => app.queue_list.next(),
+            SelectedTab::DirectoryBrowser => app.browser.next(),
+            SelectedTab::Library => app.library.next(),
+            SelectedTab::Search => app.search_results.next(),
+            SelectedTab::Playlists => app.pl_list.next(),
+        },
+
+        Action::Up => match app.selected_tab {
+            SelectedTab::Queue => app.queue_list.prev(),
+            SelectedTab::DirectoryBrowser => app.browser.prev(),
+            SelectedTab::Library => app.library.prev(),
+            SelectedTab::Search => app.search_results.prev(),
+            SelectedTab::Playlists => app.pl_list.prev(),
+        },
+
+        Action::Top => match app.selected_tab {
+            SelectedTab::Queue => app.queue_list.index = 0,
+            SelectedTab::DirectoryBrowser => app.browser.selected = 0,
+            SelectedTab::Library => app.library.selected = 0,
+            SelectedTab::Search => app.search_results.index = 0,
+            SelectedTab::Playlists => app.pl_list.index = 0,
+        },
+
+        Action::Bottom => match app.selected_tab {
+            SelectedTab::Queue => {
+                app.queue_list.index = app.queue_list.list.len().saturating_sub(1)
+            }
+            SelectedTab::DirectoryBrowser => {
+                app.browser.selected = app.browser.filetree.len().saturating_sub(1)
             }
+            SelectedTab::Library => {
+                app.library.selected = app.library.items.len().saturating_sub(1)
+            }
+            SelectedTab::Search => {
+                app.search_results.index = app.search_results.list.len().saturating_sub(1)
+            }
+            SelectedTab::Playlists => app.pl_list.index = app.pl_list.list.len().saturating_sub(1),
+        },
 
-            // Playlists tab
-            KeyCode::Char('3') => {
-                app.selected_tab = SelectedTab::Playlists;
+        Action::Select => match app.selected_tab {
+            // Play the highlighted song
+            SelectedTab::Queue => {
+                app.conn.client()?.switch(app.queue_list.index as u32)?;
+                app.conn.update_status()?;
             }
 
-            // Play next song
-            KeyCode::Char('>') => {
-                if !app.queue_list.list.is_empty() {
-                    app.conn.conn.next()?;
-                    app.update_queue();
-                    app.conn.update_status();
+            // Enter the directory, or add the song
+            SelectedTab::DirectoryBrowser => {
+                let path = app.browser.path.clone();
+                app.handle_enter()?;
+                app.conn.update_status()?;
+
+                // The filter and selection were meant for the directory we left
+                if app.browser.path != path {
+                    app.browser_filter = None;
+                    app.selection.clear();
                 }
             }
 
-            // Play previous song
-            KeyCode::Char('<') => {
-                if !app.queue_list.list.is_empty() {
-                    app.conn.conn.prev()?;
-                    app.update_queue();
-                    app.conn.update_status();
+            // Go down into the artist or album, or play the track
+            SelectedTab::Library => {
+                if app.library.level != LibraryLevel::Tracks {
+                    app.selection.clear();
                 }
+                app.handle_library_enter()?;
+                app.conn.update_status()?;
             }
 
-            // Volume controls
-            KeyCode::Char('=') | KeyCode::Char('+') => {
-                app.conn.inc_volume(2);
-                app.conn.update_status();
+            // Play the highlighted result
+            SelectedTab::Search => {
+                if let Some(song) = app.search_results.list.get(app.search_results.index) {
+                    app.enter_song(&song.clone())?;
+                    app.conn.update_status()?;
+                }
             }
 
-            KeyCode::Char('-') => {
-                app.conn.dec_volume(2);
-                app.conn.update_status();
+            // add to current playlist
+            SelectedTab::Playlists if !app.pl_list.list.is_empty() => {
+                app.conn
+                    .load_playlist(app.pl_list.list.get(app.pl_list.index).unwrap())?;
+                app.conn.update_status()?;
             }
-
-            // Toggle Mute
-            KeyCode::Char('m') => {
-                match app.conn.volume_status {
-                    VolumeStatus::Muted(v) => {
-                        app.conn.conn.volume(v)?;
-                        app.conn.volume_status = VolumeStatus::Unmuted;
-                    }
-                    VolumeStatus::Unmuted => {
-                        let current_volume = app.conn.status.volume;
-                        app.conn.conn.volume(0)?;
-                        app.conn.volume_status = VolumeStatus::Muted(current_volume);
-                    }
+            SelectedTab::Playlists => {}
+        },
+
+        // head back to previous directory
+        Action::Back => {
+            app.selection.clear();
+            match app.selected_tab {
+                SelectedTab::Library => app.library.handle_go_back(&mut app.conn)?,
+                _ => {
+                    app.browser.handle_go_back(&mut app.conn)?;
+                    app.browser_filter = None;
                 }
-                app.conn.update_status();
             }
+        }
 
-            // Update MPD database
-            KeyCode::Char('U') => {
-                app.conn.conn.rescan()?;
-                app.should_update_song_list = true;
+        Action::DeleteFromQueue if !app.selection.is_empty() => app.delete_selection()?,
+
+        // Delete highlighted song from the queue
+        Action::DeleteFromQueue => {
+            if app.queue_list.index >= app.queue_list.list.len() && app.queue_list.index != 0 {
+                app.queue_list.index -= 1;
             }
 
-            // Search for songs
-            KeyCode::Char('/') => {
-                if app.inputmode == InputMode::Normal {
-                    app.inputmode = InputMode::Editing;
+            app.conn.client()?.delete(app.queue_list.index as u32)?;
+
+            if app.queue_list.index >= app.queue_list.list.len().saturating_sub(1)
+                && app.queue_list.index != 0
+            {
+                app.queue_list.index -= 1;
+            }
+
+            app.conn.update_status()?;
+            app.update_queue()?;
+        }
+
+        // Move the selected songs together
+        Action::MoveDown | Action::MoveUp if !app.selection.is_empty() => {
+            app.move_selection(action == Action::MoveDown)?
+        }
+
+        // With a filter, swap the highlighted song with the next or previous visible one
+        Action::MoveDown | Action::MoveUp if app.queue_filter.is_some() => {
+            let current = app.queue_list.index;
+            let other = app.queue_filter.as_ref().and_then(|filter| {
+                if action == Action::MoveDown {
+                    filter.next(current)
                 } else {
-                    app.inputmode = InputMode::Normal;
+                    filter.prev(current)
                 }
+            });
+            if let Some(other) = other {
+                app.conn.client()?.swap(current as u32, other as u32)?;
+                app.queue_list.index = other;
+                app.update_queue()?;
+                app.conn.update_status()?;
             }
+        }
 
-            // Add or Remove from Current Playlist
-            KeyCode::Char(' ') => {
-                app.handle_add_or_remove_from_current_playlist()?;
-            }
-            _ => {}
+        // Swap highlighted song with next one
+        Action::MoveDown => {
+            let current: u32 = app.queue_list.index as u32;
+            let next: u32 = if (current + 1) as usize == app.queue_list.list.len() {
+                app.queue_list.index as u32
+            } else {
+                app.queue_list.index += 1;
+                current + 1
+            };
+            app.conn.client()?.swap(current, next)?;
+            app.update_queue()?;
+            app.conn.update_status()?;
         }
 
-        // Tab specific keymaps
-        match app.selected_tab {
-            SelectedTab::Queue => {
-                match key_event.code {
-                    // Go Up
-                    KeyCode::Char('j') | KeyCode::Down => app.queue_list.next(),
-
-                    // Go down
-                    KeyCode::Char('k') | KeyCode::Up => app.queue_list.prev(),
-
-                    // Next directory
-                    KeyCode::Enter | KeyCode::Char('l') | KeyCode::Right => {
-                        app.conn.conn.switch(app.queue_list.index as u32)?;
-                        app.conn.update_status();
-                    }
-
-                    // Delete highlighted song from the queue
-                    KeyCode::Char('d') => {
-                        if app.queue_list.index >= app.queue_list.list.len()
-                            && app.queue_list.index != 0
-                        {
-                            app.queue_list.index -= 1;
-                        }
-
-                        app.conn.conn.delete(app.queue_list.index as u32)?;
-
-                        if app.queue_list.index >= app.queue_list.list.len().saturating_sub(1)
-                            && app.queue_list.index != 0
-                        {
-                            app.queue_list.index -= 1;
-                        }
-
-                        app.conn.update_status();
-                        app.update_queue();
-                    }
-
-                    // Swap highlighted song with next one
-                    KeyCode::Char('J') => {
-                        let current: u32 = app.queue_list.index as u32;
-                        let next: u32 = if (current + 1) as usize == app.queue_list.list.len() {
-                            app.queue_list.index as u32
-                        } else {
-                            app.queue_list.index += 1;
-                            current + 1
-                        };
-                        app.conn.conn.swap(current, next)?;
-                        app.update_queue();
-                        app.conn.update_status();
-                    }
-
-                    // Swap highlighted song with previous one
-                    KeyCode::Char('K') => {
-                        let current: u32 = app.queue_list.index as u32;
-                        let prev: u32 = if current == 0 {
-                            app.queue_list.index as u32
-                        } else {
-                            app.queue_list.index -= 1;
-                            current - 1
-                        };
-                        app.conn.conn.swap(current, prev)?;
-                        app.update_queue();
-                        app.conn.update_status();
-                    }
-
-                    // go to top of list
-                    KeyCode::Char('g') => app.queue_list.index = 0,
-
-                    // go to bottom of list
-                    KeyCode::Char('G') => app.queue_list.index = app.queue_list.list.len() - 1,
-
-                    _ => {}
+        // Swap highlighted song with previous one
+        Action::MoveUp => {
+            let current: u32 = app.queue_list.index as u32;
+            let prev: u32 = if current == 0 {
+                app.queue_list.index as u32
+            } else {
+                app.queue_list.index -= 1;
+                current - 1
+            };
+            app.conn.client()?.swap(current, prev)?;
+            app.update_queue()?;
+            app.conn.update_status()?;
+        }
+
+        Action::RenamePlaylist if !app.pl_list.list.is_empty() => {
+            app.pl_name_prompt = PlaylistPrompt::Rename;
+            app.inputmode = InputMode::PlaylistRename;
+        }
+
+        Action::FocusContent if !app.pl_list.list.is_empty() => {
+            app.pl_content_focus = true;
+            app.pl_songs.index = 0;
+            app.update_playlist_songs()?;
+        }
+
+        // The highlighted playlist in the playlists tab, the queue elsewhere
+        Action::ExportPlaylist => {
+            let source = match app.selected_tab {
+                SelectedTab::Playlists if !app.pl_list.list.is_empty() => {
+                    Some(app.pl_list.get_item_at_current_index().clone())
                 }
-            }
+                SelectedTab::Playlists => return Ok(()),
+                _ => None,
+            };
+            let file_name = source.as_deref().unwrap_or("queue");
+            app.pl_newname_input = format!("~/{}.m3u8", file_name);
+            app.pl_cursor_pos = app.pl_newname_input.chars().count();
+            app.pl_name_prompt = PlaylistPrompt::Export(source);
+            app.inputmode = InputMode::PlaylistRename;
+        }
 
-            SelectedTab::DirectoryBrowser => {
-                match key_event.code {
-                    // Go Up
-                    KeyCode::Char('j') | KeyCode::Down => app.browser.next(),
+        Action::ImportPlaylist => {
+            app.pl_newname_input = "~/".to_string();
+            app.pl_cursor_pos = app.pl_newname_input.chars().count();
+            app.pl_name_prompt = PlaylistPrompt::Import;
+            app.inputmode = InputMode::PlaylistRename;
+        }
 
-                    // Go down
-                    KeyCode::Char('k') | KeyCode::Up => app.browser.prev(),
+        Action::PlaylistDoctor => {
+            app.scan_playlists()?;
+            app.dead_entries.reset_index();
+            app.doctor_popup = true;
+        }
 
-                    // Next directory
-                    KeyCode::Enter | KeyCode::Char('l') | KeyCode::Right => {
-                        // app.update_queue();
-                        app.handle_enter()?;
-                        app.conn.update_status();
-                    }
+        Action::SaveQueue => {
+            app.pl_name_prompt = PlaylistPrompt::SaveQueue;
+            app.inputmode = InputMode::PlaylistRename;
+        }
 
-                    // head back to previous directory
-                    KeyCode::Char('h') | KeyCode::Left => {
-                        app.browser.handle_go_back(&mut app.conn)?
-                    }
+        Action::DuplicatePlaylist if !app.pl_list.list.is_empty() => {
+            let source = app.pl_list.get_item_at_current_index().clone();
+            app.pl_newname_input = format!("{} copy", source);
+            app.pl_cursor_pos = app.pl_newname_input.chars().count();
+            app.pl_name_prompt = PlaylistPrompt::Duplicate(source);
+            app.inputmode = InputMode::PlaylistRename;
+        }
 
-                    // go to top of list
-                    KeyCode::Char('g') => app.browser.selected = 0,
+        Action::DeletePlaylist if !app.pl_list.list.is_empty() => {
+            let names = app.selected_playlists();
+            app.ask_confirmation(Confirmation::DeletePlaylists(names));
+        }
 
-                    // go to bottom of list
-                    KeyCode::Char('G') => app.browser.selected = app.browser.filetree.len() - 1,
+        Action::ClearPlaylist if !app.pl_list.list.is_empty() => {
+            let names = app.selected_playlists();
+            app.ask_confirmation(Confirmation::ClearPlaylists(names));
+        }
 
-                    _ => {}
-                }
-            }
+        // Library filters and playback
+        Action::FilterGenre => {
+            app.selection.clear();
+            app.library
+                .open_filter(LibraryLevel::Genres, &mut app.conn)?
+        }
+        Action::FilterDate => {
+            app.selection.clear();
+            app.library
+                .open_filter(LibraryLevel::Dates, &mut app.conn)?
+        }
+        Action::Play => {
+            app.play_library_selection()?;
+            app.conn.update_status()?;
+        }
 
-            SelectedTab::Playlists => {
-                match key_event.code {
-                    // Go Up
-                    KeyCode::Char('j') | KeyCode::Down => app.pl_list.next(),
-
-                    // Go down
-                    KeyCode::Char('k') | KeyCode::Up => app.pl_list.prev(),
-
-                    // go to top of list
-                    KeyCode::Char('g') => app.pl_list.index = 0,
-
-                    // go to bottom of list
-                    KeyCode::Char('G') => app.pl_list.index = app.pl_list.list.len() - 1,
-
-                    // Playlist Rename
-                    KeyCode::Char('R') => {
-                        app.inputmode = InputMode::PlaylistRename;
-                    }
-
-                    // add to current playlist
-                    KeyCode::Enter | KeyCode::Char('l') | KeyCode::Right | KeyCode::Char(' ') => {
-                        // app.update_queue();
-                        if !app.pl_list.list.is_empty() {
-                            app.conn
-                                .load_playlist(app.pl_list.list.get(app.pl_list.index).unwrap())?;
-                            app.conn.update_status();
-                        }
-                    }
-                    _ => {}
-                }
-            }
+        // Add every search result to the queue
+        Action::AddAll => {
+            let sources = Source::from_songs(&app.search_results.list);
+            app.adder.add(Destination::Queue, sources);
         }
+
+        _ => {}
     }
     Ok(())
 }
@@ -331,5 +550,5 @@ pub fn handle_mouse_events(mouse_event: MouseEvent, app: &mut App) -> AppResult<
         }
         _ => {}
     }
-    Ok(())
+    app.cache_highlighted_playlist()
 }
@@ -0,0 +1,847 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Everything a key can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    // Global
+    Quit,
+    ClearQueue,
+    TogglePause,
+    Pause,
+    ToggleRepeat,
+    ToggleRandom,
+    Dmenu,
+    AddToPlaylist,
+    SeekForward,
+    SeekBackward,
+    NextTab,
+    QueueTab,
+    BrowserTab,
+    LibraryTab,
+    PlaylistsTab,
+    SearchTab,
+    NextSong,
+    PreviousSong,
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    UpdateDatabase,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    DatabaseSearch,
+    ToggleInQueue,
+
+    // Lists
+    Down,
+    Up,
+    Top,
+    Bottom,
+    Select,
+    Back,
+    DeleteFromQueue,
+    MoveDown,
+    MoveUp,
+    RenamePlaylist,
+    FocusContent,
+    DeletePlaylist,
+    ClearPlaylist,
+    DuplicatePlaylist,
+    SaveQueue,
+    ExportPlaylist,
+    ImportPlaylist,
+    PlaylistDoctor,
+    FixEntry,
+    FixAll,
+    PruneEntry,
+    PruneAll,
+    FilterGenre,
+    FilterDate,
+    Play,
+    AddAll,
+    Filter,
+    ClearFilter,
+    ToggleMark,
+    VisualMode,
+    PlayNext,
+    Add,
+    AddAndPlay,
+    Close,
+
+    // Input modes
+    Cancel,
+    Confirm,
+    CursorLeft,
+    CursorRight,
+    DeleteChar,
+    CycleSearchMode,
+    ToggleCaseSensitive,
+
+    /// Unbinds a default key
+    None,
+}
+
+/// Names of the actions in the configuration file
+const ACTION_NAMES: &[(&str, Action)] = &[
+    ("quit", Action::Quit),
+    ("clear_queue", Action::ClearQueue),
+    ("toggle_pause", Action::TogglePause),
+    ("pause", Action::Pause),
+    ("toggle_repeat", Action::ToggleRepeat),
+    ("toggle_random", Action::ToggleRandom),
+    ("dmenu", Action::Dmenu),
+    ("add_to_playlist", Action::AddToPlaylist),
+    ("seek_forward", Action::SeekForward),
+    ("seek_backward", Action::SeekBackward),
+    ("next_tab", Action::NextTab),
+    ("queue_tab", Action::QueueTab),
+    ("browser_tab", Action::BrowserTab),
+    ("library_tab", Action::LibraryTab),
+    ("playlists_tab", Action::PlaylistsTab),
+    ("search_tab", Action::SearchTab),
+    ("next_song", Action::NextSong),
+    ("previous_song", Action::PreviousSong),
+    ("volume_up", Action::VolumeUp),
+    ("volume_down", Action::VolumeDown),
+    ("toggle_mute", Action::ToggleMute),
+    ("update_database", Action::UpdateDatabase),
+    ("search", Action::Search),
+    ("next_match", Action::NextMatch),
+    ("previous_match", Action::PreviousMatch),
+    ("database_search", Action::DatabaseSearch),
+    ("toggle_in_queue", Action::ToggleInQueue),
+    ("down", Action::Down),
+    ("up", Action::Up),
+    ("top", Action::Top),
+    ("bottom", Action::Bottom),
+    ("select", Action::Select),
+    ("back", Action::Back),
+    ("delete_from_queue", Action::DeleteFromQueue),
+    ("move_down", Action::MoveDown),
+    ("move_up", Action::MoveUp),
+    ("rename_playlist", Action::RenamePlaylist),
+    ("focus_content", Action::FocusContent),
+    ("delete_playlist", Action::DeletePlaylist),
+    ("clear_playlist", Action::ClearPlaylist),
+    ("duplicate_playlist", Action::DuplicatePlaylist),
+    ("save_queue", Action::SaveQueue),
+    ("export_playlist", Action::ExportPlaylist),
+    ("import_playlist", Action::ImportPlaylist),
+    ("playlist_doctor", Action::PlaylistDoctor),
+    ("fix_entry", Action::FixEntry),
+    ("fix_all", Action::FixAll),
+    ("prune_entry", Action::PruneEntry),
+    ("prune_all", Action::PruneAll),
+    ("filter_genre", Action::FilterGenre),
+    ("filter_date", Action::FilterDate),
+    ("play", Action::Play),
+    ("add_all", Action::AddAll),
+    ("filter", Action::Filter),
+    ("clear_filter", Action::ClearFilter),
+    ("toggle_mark", Action::ToggleMark),
+    ("visual_mode", Action::VisualMode),
+    ("play_next", Action::PlayNext),
+    ("add", Action::Add),
+    ("add_and_play", Action::AddAndPlay),
+    ("close", Action::Close),
+    ("cancel", Action::Cancel),
+    ("confirm", Action::Confirm),
+    ("cursor_left", Action::CursorLeft),
+    ("cursor_right", Action::CursorRight),
+    ("delete_char", Action::DeleteChar),
+    ("cycle_search_mode", Action::CycleSearchMode),
+    ("toggle_case_sensitive", Action::ToggleCaseSensitive),
+    ("none", Action::None),
+];
+
+impl Action {
+    /// Whether the action can be bound in the given context
+    pub fn allowed_in(self, context: Context) -> bool {
+        match self {
+            Action::None => true,
+            Action::Cancel
+            | Action::Confirm
+            | Action::CursorLeft
+            | Action::CursorRight
+            | Action::DeleteChar
+            | Action::CycleSearchMode
+            | Action::ToggleCaseSensitive => context == Context::Input,
+            Action::Down | Action::Up | Action::Top | Action::Bottom | Action::Select => {
+                context != Context::Input
+            }
+            Action::Close => matches!(context, Context::Popup | Context::Doctor),
+            Action::Back => matches!(
+                context,
+                Context::DirectoryBrowser | Context::Library | Context::PlaylistContent
+            ),
+            Action::DeleteFromQueue | Action::MoveDown | Action::MoveUp => {
+                matches!(context, Context::Queue | Context::PlaylistContent)
+            }
+            Action::RenamePlaylist
+            | Action::FocusContent
+            | Action::DeletePlaylist
+            | Action::ClearPlaylist
+            | Action::DuplicatePlaylist
+            | Action::ImportPlaylist
+            | Action::PlaylistDoctor => context == Context::Playlists,
+            Action::FixEntry | Action::FixAll | Action::PruneEntry | Action::PruneAll => {
+                context == Context::Doctor
+            }
+            Action::ExportPlaylist => matches!(context, Context::Queue | Context::Playlists),
+            Action::FilterGenre | Action::FilterDate | Action::Play => context == Context::Library,
+            Action::AddAll => context == Context::Search,
+            Action::Add | Action::AddAndPlay => matches!(
+                context,
+                Context::DirectoryBrowser
+                    | Context::Library
+                    | Context::Search
+                    | Context::Playlists
+                    | Context::PlaylistContent
+            ),
+            Action::Filter => matches!(
+                context,
+                Context::Queue | Context::DirectoryBrowser | Context::Playlists
+            ),
+            Action::ClearFilter | Action::ToggleMark | Action::VisualMode | Action::PlayNext => {
+                !matches!(context, Context::Global | Context::Input | Context::Popup)
+            }
+            _ => !matches!(context, Context::Input | Context::Popup),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ACTION_NAMES
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, action)| *action)
+            .ok_or_else(|| format!("unknown action \"{}\"", s))
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = ACTION_NAMES
+            .iter()
+            .find(|(_, action)| action == self)
+            .map_or("none", |(name, _)| name);
+        write!(f, "{}", name)
+    }
+}
+
+/// Where a key is pressed, each one has its own bindings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Context {
+    /// Any tab, when the tab itself does not bind the key
+    Global,
+    Queue,
+    DirectoryBrowser,
+    /// Library browser, by tags
+    Library,
+    Playlists,
+    /// Songs of the highlighted playlist, once focused
+    PlaylistContent,
+    /// Results of the database search
+    Search,
+    /// The "add to playlist" popup
+    Popup,
+    /// Songs of the playlists missing from the database
+    Doctor,
+    /// Search prompt and playlist name inputs
+    Input,
+}
+
+/// A single key press, with its modifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shift is already part of the character, and of BackTab
+        match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => Key {
+                code: KeyCode::Char(c.to_ascii_uppercase()),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            KeyCode::BackTab => Key {
+                code,
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            _ => Key { code, modifiers },
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    /// Writes the key the way it is bound: `f`, `<Esc>`, `<C-c>`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char('<') => "lt".to_string(),
+            KeyCode::Char('>') => "gt".to_string(),
+            KeyCode::Char(c) if self.modifiers.is_empty() => return write!(f, "{}", c),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Enter => "Enter".to_string(),
+            KeyCode::Esc => "Esc".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::BackTab => "S-Tab".to_string(),
+            KeyCode::Backspace => "BS".to_string(),
+            KeyCode::Delete => "Del".to_string(),
+            KeyCode::Insert => "Insert".to_string(),
+            KeyCode::Up => "Up".to_string(),
+            KeyCode::Down => "Down".to_string(),
+            KeyCode::Left => "Left".to_string(),
+            KeyCode::Right => "Right".to_string(),
+            KeyCode::Home => "Home".to_string(),
+            KeyCode::End => "End".to_string(),
+            KeyCode::PageUp => "PageUp".to_string(),
+            KeyCode::PageDown => "PageDown".to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            code => format!("{:?}", code),
+        };
+        let mut modifiers = String::new();
+        for (modifier, prefix) in [
+            (KeyModifiers::CONTROL, "C-"),
+            (KeyModifiers::ALT, "A-"),
+            (KeyModifiers::SHIFT, "S-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                modifiers.push_str(prefix);
+            }
+        }
+        write!(f, "<{}{}>", modifiers, name)
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Key::new(event.code, event.modifiers)
+    }
+}
+
+/// Parse a key sequence written the way vim does: `gg`, `<C-c>`, `<Space>`, `<S-Tab>`
+///
+/// A `<` which does not start a key name is taken literally.
+pub fn parse_keys(s: &str) -> Result<Vec<Key>, String> {
+    let mut keys = Vec::new();
+    let mut rest = s;
+
+    while let Some(c) = rest.chars().next() {
+        let bracketed = match (c, rest.find('>')) {
+            ('<', Some(end)) if end > 1 => Some((&rest[1..end], end + 1)),
+            _ => None,
+        };
+        match bracketed {
+            Some((name, len)) => {
+                keys.push(parse_key_name(name)?);
+                rest = &rest[len..];
+            }
+            None => {
+                keys.push(Key::new(KeyCode::Char(c), KeyModifiers::NONE));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    if keys.is_empty() {
+        return Err("empty key sequence".to_string());
+    }
+    Ok(keys)
+}
+
+/// Parse what is inside `<…>`
+fn parse_key_name(s: &str) -> Result<Key, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = s;
+    while name.len() > 2 && name.as_bytes()[1] == b'-' {
+        modifiers |= match name.as_bytes()[0].to_ascii_uppercase() {
+            b'C' => KeyModifiers::CONTROL,
+            b'A' | b'M' => KeyModifiers::ALT,
+            b'S' => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier in \"<{}>\"", s)),
+        };
+        name = &name[2..];
+    }
+
+    let code = match name.to_lowercase().as_str() {
+        "enter" | "cr" | "return" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+        "tab" => KeyCode::Tab,
+        "space" => KeyCode::Char(' '),
+        "bs" | "backspace" => KeyCode::Backspace,
+        "del" | "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "lt" => KeyCode::Char('<'),
+        "gt" => KeyCode::Char('>'),
+        lower => match lower.strip_prefix('f').map(str::parse::<u8>) {
+            Some(Ok(n)) if (1..=12).contains(&n) => KeyCode::F(n),
+            _ if name.chars().count() == 1 => KeyCode::Char(name.chars().next().unwrap()),
+            _ => return Err(format!("unknown key \"<{}>\"", s)),
+        },
+    };
+
+    Ok(Key::new(code, modifiers))
+}
+
+/// Outcome of looking up the keys pressed so far
+#[derive(Debug, PartialEq)]
+pub enum Lookup {
+    Action(Action),
+    /// The keys start a longer sequence, wait for the next one
+    Pending,
+    NotFound,
+}
+
+/// Bindings from key sequences to actions, for every context
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "KeyBindings")]
+pub struct Keymap {
+    bindings: HashMap<Context, HashMap<Vec<Key>, Action>>,
+}
+
+impl Keymap {
+    /// Find the action bound to the keys, trying the contexts in order
+    pub fn lookup(&self, contexts: &[Context], keys: &[Key]) -> Lookup {
+        let maps = || contexts.iter().filter_map(|c| self.bindings.get(c));
+
+        for map in maps() {
+            if let Some(action) = map.get(keys) {
+                return Lookup::Action(*action);
+            }
+        }
+
+        let is_prefix = maps().any(|map| {
+            map.iter()
+                .any(|(seq, action)| *action != Action::None && seq.starts_with(keys))
+        });
+        if is_prefix {
+            Lookup::Pending
+        } else {
+            Lookup::NotFound
+        }
+    }
+
+    /// Find the action bound to a single key in the context
+    pub fn get(&self, context: Context, key: Key) -> Option<Action> {
+        self.bindings.get(&context)?.get(&[key][..]).copied()
+    }
+
+    /// The shortest key sequence bound to the action in the context, to show in hints
+    pub fn keys_for(&self, context: Context, action: Action) -> Option<String> {
+        self.bindings
+            .get(&context)?
+            .iter()
+            .filter(|(_, bound)| **bound == action)
+            .map(|(keys, _)| keys.iter().map(Key::to_string).collect::<String>())
+            .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+    }
+
+    /// Bind `keys` to `action` in the context, replacing any previous binding
+    pub fn bind(&mut self, context: Context, keys: &str, action: Action) -> Result<(), String> {
+        if !action.allowed_in(context) {
+            return Err(format!(
+                "action \"{}\" can not be used in [keys.{}]",
+                action,
+                context_name(context)
+            ));
+        }
+        let keys = parse_keys(keys)?;
+        self.bindings
+            .entry(context)
+            .or_default()
+            .insert(keys, action);
+        Ok(())
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Keymap {
+            bindings: HashMap::new(),
+        };
+        for (context, keys, action) in DEFAULT_BINDINGS {
+            keymap
+                .bind(*context, keys, *action)
+                .expect("invalid default key binding");
+        }
+        for context in [
+            Context::Queue,
+            Context::DirectoryBrowser,
+            Context::Library,
+            Context::Playlists,
+            Context::PlaylistContent,
+            Context::Search,
+            Context::Popup,
+            Context::Doctor,
+        ] {
+            for (keys, action) in LIST_BINDINGS {
+                keymap
+                    .bind(context, keys, *action)
+                    .expect("invalid default key binding");
+            }
+            if matches!(
+                context,
+                Context::Popup | Context::PlaylistContent | Context::Doctor
+            ) {
+                continue;
+            }
+            for (keys, action) in SELECTION_BINDINGS {
+                keymap
+                    .bind(context, keys, *action)
+                    .expect("invalid default key binding");
+            }
+        }
+        keymap
+    }
+}
+
+/// Moving around, the same in every list
+const LIST_BINDINGS: &[(&str, Action)] = &[
+    ("j", Action::Down),
+    ("<Down>", Action::Down),
+    ("k", Action::Up),
+    ("<Up>", Action::Up),
+    ("gg", Action::Top),
+    ("G", Action::Bottom),
+];
+
+/// Selecting rows for the bulk actions, the same in every tab
+const SELECTION_BINDINGS: &[(&str, Action)] = &[
+    ("x", Action::ToggleMark),
+    ("v", Action::VisualMode),
+    ("i", Action::PlayNext),
+    ("<Esc>", Action::ClearFilter),
+];
+
+const DEFAULT_BINDINGS: &[(Context, &str, Action)] = &[
+    (Context::Global, "q", Action::Quit),
+    (Context::Global, "<C-c>", Action::Quit),
+    (Context::Global, "c", Action::ClearQueue),
+    (Context::Global, "C", Action::ClearQueue),
+    (Context::Global, "p", Action::TogglePause),
+    (Context::Global, "s", Action::Pause),
+    (Context::Global, "r", Action::ToggleRepeat),
+    (Context::Global, "z", Action::ToggleRandom),
+    (Context::Global, "D", Action::Dmenu),
+    (Context::Global, "a", Action::AddToPlaylist),
+    (Context::Global, "f", Action::SeekForward),
+    (Context::Global, "b", Action::SeekBackward),
+    (Context::Global, "<Tab>", Action::NextTab),
+    (Context::Global, "1", Action::QueueTab),
+    (Context::Global, "2", Action::BrowserTab),
+    (Context::Global, "3", Action::PlaylistsTab),
+    (Context::Global, "4", Action::LibraryTab),
+    (Context::Global, "5", Action::SearchTab),
+    (Context::Global, ">", Action::NextSong),
+    (Context::Global, "<", Action::PreviousSong),
+    (Context::Global, "=", Action::VolumeUp),
+    (Context::Global, "+", Action::VolumeUp),
+    (Context::Global, "-", Action::VolumeDown),
+    (Context::Global, "m", Action::ToggleMute),
+    (Context::Global, "U", Action::UpdateDatabase),
+    (Context::Global, "/", Action::Search),
+    (Context::Global, "n", Action::NextMatch),
+    (Context::Global, "N", Action::PreviousMatch),
+    (Context::Global, "S", Action::DatabaseSearch),
+    (Context::Global, "W", Action::SaveQueue),
+    (Context::Global, "<Space>", Action::ToggleInQueue),
+    (Context::Queue, "<Enter>", Action::Select),
+    (Context::Queue, "l", Action::Select),
+    (Context::Queue, "<Right>", Action::Select),
+    (Context::Queue, "d", Action::DeleteFromQueue),
+    (Context::Queue, "J", Action::MoveDown),
+    (Context::Queue, "K", Action::MoveUp),
+    (Context::Queue, "F", Action::Filter),
+    (Context::Queue, "X", Action::ExportPlaylist),
+    (Context::DirectoryBrowser, "<Enter>", Action::Select),
+    (Context::DirectoryBrowser, "l", Action::Select),
+    (Context::DirectoryBrowser, "<Right>", Action::Select),
+    (Context::DirectoryBrowser, "h", Action::Back),
+    (Context::DirectoryBrowser, "<Left>", Action::Back),
+    (Context::DirectoryBrowser, "F", Action::Filter),
+    (Context::DirectoryBrowser, "o", Action::Add),
+    (Context::DirectoryBrowser, "O", Action::AddAndPlay),
+    (Context::Library, "<Enter>", Action::Select),
+    (Context::Library, "l", Action::Select),
+    (Context::Library, "<Right>", Action::Select),
+    (Context::Library, "h", Action::Back),
+    (Context::Library, "<Left>", Action::Back),
+    (Context::Library, "P", Action::Play),
+    (Context::Library, "F", Action::FilterGenre),
+    (Context::Library, "Y", Action::FilterDate),
+    (Context::Library, "o", Action::Add),
+    (Context::Library, "O", Action::AddAndPlay),
+    (Context::Playlists, "<Enter>", Action::Select),
+    (Context::Playlists, "l", Action::FocusContent),
+    (Context::Playlists, "<Right>", Action::FocusContent),
+    (Context::Playlists, "<Space>", Action::Select),
+    (Context::Playlists, "R", Action::RenamePlaylist),
+    (Context::Playlists, "d", Action::DeletePlaylist),
+    (Context::Playlists, "E", Action::ClearPlaylist),
+    (Context::Playlists, "y", Action::DuplicatePlaylist),
+    (Context::Playlists, "X", Action::ExportPlaylist),
+    (Context::Playlists, "I", Action::ImportPlaylist),
+    (Context::Playlists, "H", Action::PlaylistDoctor),
+    (Context::Playlists, "F", Action::Filter),
+    (Context::Playlists, "o", Action::Add),
+    (Context::Playlists, "O", Action::AddAndPlay),
+    (Context::PlaylistContent, "<Enter>", Action::Select),
+    (Context::PlaylistContent, "l", Action::Select),
+    (Context::PlaylistContent, "<Right>", Action::Select),
+    (Context::PlaylistContent, "h", Action::Back),
+    (Context::PlaylistContent, "<Left>", Action::Back),
+    (Context::PlaylistContent, "<Esc>", Action::Back),
+    (Context::PlaylistContent, "d", Action::DeleteFromQueue),
+    (Context::PlaylistContent, "J", Action::MoveDown),
+    (Context::PlaylistContent, "K", Action::MoveUp),
+    (Context::PlaylistContent, "o", Action::Add),
+    (Context::PlaylistContent, "O", Action::AddAndPlay),
+    (Context::Search, "<Enter>", Action::Select),
+    (Context::Search, "l", Action::Select),
+    (Context::Search, "<Right>", Action::Select),
+    (Context::Search, "A", Action::AddAll),
+    (Context::Search, "o", Action::Add),
+    (Context::Search, "O", Action::AddAndPlay),
+    (Context::Popup, "q", Action::Close),
+    (Context::Popup, "<Esc>", Action::Close),
+    (Context::Popup, "<Enter>", Action::Select),
+    (Context::Doctor, "q", Action::Close),
+    (Context::Doctor, "<Esc>", Action::Close),
+    (Context::Doctor, "<Enter>", Action::FixEntry),
+    (Context::Doctor, "f", Action::FixEntry),
+    (Context::Doctor, "F", Action::FixAll),
+    (Context::Doctor, "d", Action::PruneEntry),
+    (Context::Doctor, "D", Action::PruneAll),
+    (Context::Input, "<Esc>", Action::Cancel),
+    (Context::Input, "<Enter>", Action::Confirm),
+    (Context::Input, "<Left>", Action::CursorLeft),
+    (Context::Input, "<Right>", Action::CursorRight),
+    (Context::Input, "<BS>", Action::DeleteChar),
+    (Context::Input, "<C-t>", Action::CycleSearchMode),
+    (Context::Input, "<C-s>", Action::ToggleCaseSensitive),
+];
+
+fn context_name(context: Context) -> &'static str {
+    match context {
+        Context::Global => "global",
+        Context::Queue => "queue",
+        Context::DirectoryBrowser => "browser",
+        Context::Library => "library",
+        Context::Playlists => "playlists",
+        Context::PlaylistContent => "playlist_content",
+        Context::Search => "search",
+        Context::Popup => "popup",
+        Context::Doctor => "doctor",
+        Context::Input => "input",
+    }
+}
+
+/// `[keys]` table of the configuration file, merged over the default bindings
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct KeyBindings {
+    global: HashMap<String, String>,
+    queue: HashMap<String, String>,
+    browser: HashMap<String, String>,
+    library: HashMap<String, String>,
+    playlists: HashMap<String, String>,
+    playlist_content: HashMap<String, String>,
+    search: HashMap<String, String>,
+    popup: HashMap<String, String>,
+    doctor: HashMap<String, String>,
+    input: HashMap<String, String>,
+}
+
+impl TryFrom<KeyBindings> for Keymap {
+    type Error = String;
+
+    fn try_from(config: KeyBindings) -> Result<Self, Self::Error> {
+        let mut keymap = Keymap::default();
+        for (context, bindings) in [
+            (Context::Global, config.global),
+            (Context::Queue, config.queue),
+            (Context::DirectoryBrowser, config.browser),
+            (Context::Library, config.library),
+            (Context::Playlists, config.playlists),
+            (Context::PlaylistContent, config.playlist_content),
+            (Context::Search, config.search),
+            (Context::Popup, config.popup),
+            (Context::Doctor, config.doctor),
+            (Context::Input, config.input),
+        ] {
+            for (keys, action) in bindings {
+                keymap.bind(context, &keys, action.parse()?)?;
+            }
+        }
+        Ok(keymap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names_round_trip() {
+        for keys in [
+            "f", "F", "gg", "<Esc>", "<Enter>", "<Space>", "<C-c>", "<S-Tab>", "<lt>", "<F5>",
+        ] {
+            let parsed = parse_keys(keys).unwrap();
+            let written: String = parsed.iter().map(Key::to_string).collect();
+            assert_eq!(written, keys);
+        }
+    }
+
+    #[test]
+    fn keys_for_prefers_the_shortest() {
+        let mut keymap = Keymap::default();
+        assert_eq!(
+            keymap
+                .keys_for(Context::Doctor, Action::FixEntry)
+                .as_deref(),
+            Some("f")
+        );
+        keymap.bind(Context::Doctor, "f", Action::None).unwrap();
+        assert_eq!(
+            keymap
+                .keys_for(Context::Doctor, Action::FixEntry)
+                .as_deref(),
+            Some("<Enter>")
+        );
+        assert_eq!(keymap.keys_for(Context::Doctor, Action::Quit), None);
+    }
+
+    fn keys(s: &str) -> Vec<Key> {
+        parse_keys(s).unwrap()
+    }
+
+    #[test]
+    fn lookup_waits_for_the_whole_sequence() {
+        let keymap = Keymap::default();
+        let contexts = [Context::Queue, Context::Global];
+        assert_eq!(keymap.lookup(&contexts, &keys("g")), Lookup::Pending);
+        assert_eq!(
+            keymap.lookup(&contexts, &keys("gg")),
+            Lookup::Action(Action::Top)
+        );
+        assert_eq!(keymap.lookup(&contexts, &keys("gx")), Lookup::NotFound);
+        assert_eq!(
+            keymap.lookup(&contexts, &keys("q")),
+            Lookup::Action(Action::Quit)
+        );
+    }
+
+    #[test]
+    fn lookup_prefers_the_first_context() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.lookup(&[Context::Playlists, Context::Global], &keys("<Space>")),
+            Lookup::Action(Action::Select)
+        );
+        assert_eq!(
+            keymap.lookup(&[Context::Queue, Context::Global], &keys("<Space>")),
+            Lookup::Action(Action::ToggleInQueue)
+        );
+    }
+
+    #[test]
+    fn unbound_sequences_are_not_pending() {
+        let mut keymap = Keymap::default();
+        keymap.bind(Context::Queue, "gg", Action::None).unwrap();
+        assert_eq!(
+            keymap.lookup(&[Context::Queue], &keys("g")),
+            Lookup::NotFound
+        );
+        assert_eq!(
+            keymap.lookup(&[Context::Queue], &keys("gg")),
+            Lookup::Action(Action::None)
+        );
+    }
+
+    #[test]
+    fn invalid_key_strings() {
+        assert!(parse_keys("").is_err());
+        assert!(parse_keys("<Foo>").is_err());
+        assert!(parse_keys("<X-a>").is_err());
+        assert!(parse_keys("g<F13>").is_err());
+        // A < which does not start a key name is taken literally
+        assert_eq!(
+            keys("<>"),
+            vec![
+                Key::new(KeyCode::Char('<'), KeyModifiers::NONE),
+                Key::new(KeyCode::Char('>'), KeyModifiers::NONE),
+            ]
+        );
+        assert_eq!(
+            keys("<S-a>"),
+            vec![Key::new(KeyCode::Char('A'), KeyModifiers::NONE)]
+        );
+    }
+
+    #[test]
+    fn bind_refuses_actions_of_other_contexts() {
+        let mut keymap = Keymap::default();
+        assert!(keymap.bind(Context::Popup, "x", Action::AddAll).is_err());
+        assert!(keymap.bind(Context::Search, "x", Action::AddAll).is_ok());
+    }
+
+    #[test]
+    fn user_bindings_replace_the_defaults() {
+        let keymap: Keymap = toml::from_str(
+            r#"
+            [global]
+            q = "toggle_pause"
+            "<C-q>" = "quit"
+            [queue]
+            gg = "none"
+            "#,
+        )
+        .unwrap();
+        let contexts = [Context::Queue, Context::Global];
+        assert_eq!(
+            keymap.lookup(&contexts, &keys("q")),
+            Lookup::Action(Action::TogglePause)
+        );
+        assert_eq!(
+            keymap.lookup(&contexts, &keys("<C-q>")),
+            Lookup::Action(Action::Quit)
+        );
+        // The other defaults are kept
+        assert_eq!(
+            keymap.lookup(&contexts, &keys("p")),
+            Lookup::Action(Action::TogglePause)
+        );
+        assert_eq!(keymap.lookup(&contexts, &keys("g")), Lookup::NotFound);
+    }
+
+    #[test]
+    fn user_bindings_are_checked() {
+        for config in [
+            "[global]\nq = \"frobnicate\"",
+            "[global]\n\"<Foo>\" = \"quit\"",
+            "[popup]\nx = \"add_all\"",
+            "[nowhere]\nq = \"quit\"",
+        ] {
+            assert!(toml::from_str::<Keymap>(config).is_err(), "{}", config);
+        }
+    }
+}
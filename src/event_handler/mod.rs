@@ -1,6 +1,12 @@
 pub mod event;
 pub mod handler;
+pub mod keymap;
 pub mod search_keys;
 pub mod pl_rename_keys;
 pub mod pl_append_keys;
 pub mod new_pl_keys;
+pub mod db_search_keys;
+pub mod filter_keys;
+pub mod confirm_keys;
+pub mod pl_content_keys;
+pub mod doctor_keys;
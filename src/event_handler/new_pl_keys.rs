@@ -1,47 +1,45 @@
 use crate::{
+    adder::Destination,
     app::{App, AppResult},
+    event_handler::keymap::{Action, Context},
     ui::InputMode,
 };
 use crossterm::event::{KeyCode, KeyEvent};
 
 pub fn handle_new_pl_keys(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
-    match key_event.code {
-        KeyCode::Esc => {
+    match app.config.keys.get(Context::Input, key_event.into()) {
+        Some(Action::Cancel) => {
             app.pl_new_pl_input.clear();
             app.reset_cursor();
             app.inputmode = InputMode::Normal;
         }
-        KeyCode::Char(to_insert) => {
-            app.enter_char(to_insert);
-        }
-        KeyCode::Enter => {
-            let pl_name = &app.pl_new_pl_input;
-
-            for song in app.pl_new_pl_songs_buffer.iter() {
-                app.conn.conn.pl_push(pl_name, song)?;
-            }
-            app.pl_new_pl_input.clear();
-
-            app.pl_list.list = App::get_playlist(&mut app.conn.conn)?;
-            app.append_list = App::get_append_list(&mut app.conn.conn)?;
+        Some(Action::Confirm) => {
+            let pl_name = std::mem::take(&mut app.pl_new_pl_input);
+            let sources = std::mem::take(&mut app.pl_new_pl_songs_buffer);
+            // The lists are refreshed once the stored_playlist event comes
+            app.adder.add(Destination::Playlist(pl_name), sources);
 
             app.reset_cursor();
             app.inputmode = InputMode::Normal;
         }
 
-        KeyCode::Backspace => {
+        Some(Action::DeleteChar) => {
             app.delete_char();
         }
 
-        KeyCode::Left => {
+        Some(Action::CursorLeft) => {
             app.move_cursor_left();
         }
 
-        KeyCode::Right => {
+        Some(Action::CursorRight) => {
             app.move_cursor_right();
         }
 
-        _ => {}
+        _ => {
+            if let KeyCode::Char(to_insert) = key_event.code {
+                app.enter_char(to_insert);
+            }
+        }
     }
     Ok(())
 }
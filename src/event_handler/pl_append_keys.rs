@@ -1,19 +1,38 @@
+use crate::adder::{Destination, Source};
 use crate::app::{App, AppResult, SelectedTab};
+use crate::event_handler::keymap::Action;
 use crate::ui::InputMode;
-use crate::utils::FileExtension;
-use crossterm::event::{KeyCode, KeyEvent};
-use std::path::Path;
 
-pub fn hande_pl_append_keys(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
-    match key_event.code {
-        KeyCode::Char('q') | KeyCode::Esc => {
+pub fn hande_pl_append_keys(action: Action, app: &mut App) -> AppResult<()> {
+    match action {
+        Action::Close => {
             app.playlist_popup = false;
         }
 
-        KeyCode::Char('j') | KeyCode::Down => app.append_list.next(),
-        KeyCode::Char('k') | KeyCode::Up => app.append_list.prev(),
+        Action::Down => app.append_list.next(),
+        Action::Up => app.append_list.prev(),
+        Action::Top => app.append_list.index = 0,
+        Action::Bottom => app.append_list.index = app.append_list.list.len().saturating_sub(1),
 
-        KeyCode::Enter => {
+        Action::Select if !app.selection.is_empty() => {
+            // The whole selection goes to the highlighted playlist
+            let pl_name = app.append_list.get_item_at_current_index().clone();
+            let sources = app.selection_sources()?;
+            if pl_name == "Current Playlist" {
+                app.adder.add(Destination::Queue, sources);
+            } else if pl_name == "New Playlist" {
+                app.pl_new_pl_songs_buffer = sources;
+                app.inputmode = InputMode::NewPlaylist;
+            } else {
+                app.adder.add(Destination::Playlist(pl_name), sources);
+            }
+
+            app.selection.clear();
+            app.playlist_popup = false;
+            app.append_list.index = 0;
+        }
+
+        Action::Select => {
             // name of highlighted playlist in append list
             let pl_name = &app.append_list.get_item_at_current_index();
 
@@ -25,15 +44,14 @@ pub fn hande_pl_append_keys(key_event: KeyEvent, app: &mut App) -> AppResult<()>
                         return Ok(());
                     }
 
-                    if let Ok(songs) = app.conn.conn.songs(app.queue_list.index as u32) {
+                    if let Ok(songs) = app.conn.client()?.songs(app.queue_list.index as u32) {
                         let option_song = songs.first();
                         if let Some(song) = option_song {
                             if *pl_name == "Current Playlist" {
-                                app.conn.conn.push(song)?;
-                                app.update_queue();
+                                app.conn.client()?.push(song)?;
+                                app.update_queue()?;
                             } else if *pl_name == "New Playlist" {
-                                app.pl_new_pl_songs_buffer.clear();
-                                app.pl_new_pl_songs_buffer.push(song.clone());
+                                app.pl_new_pl_songs_buffer = vec![Source::File(song.file.clone())];
                                 app.inputmode = InputMode::NewPlaylist;
                             } else {
                                 app.conn.add_to_playlist(pl_name, song)?;
@@ -43,62 +61,100 @@ pub fn hande_pl_append_keys(key_event: KeyEvent, app: &mut App) -> AppResult<()>
                 }
 
                 SelectedTab::DirectoryBrowser => {
-                    let (t, f) = app.browser.filetree.get(app.browser.selected).unwrap();
+                    let Some((t, f)) = app.browser.filetree.get(app.browser.selected) else {
+                        app.playlist_popup = false;
+                        return Ok(());
+                    };
                     if t == "file" {
                         let short_path = f;
                         if let Some(full_path) = app.conn.get_full_path(short_path) {
                             let song = app.conn.get_song_with_only_filename(full_path);
 
                             if *pl_name == "Current Playlist" {
-                                app.conn.conn.push(&song)?;
-                                app.update_queue();
+                                app.conn.client()?.push(&song)?;
+                                app.update_queue()?;
                             } else if *pl_name == "New Playlist" {
-                                app.pl_new_pl_songs_buffer.clear();
-                                app.pl_new_pl_songs_buffer.push(song.clone());
+                                app.pl_new_pl_songs_buffer = vec![Source::File(song.file.clone())];
                                 app.inputmode = InputMode::NewPlaylist;
                             } else {
                                 app.conn.add_to_playlist(pl_name, &song)?;
                             }
                         }
                     } else if t == "directory" {
-                        let file = format!("{}/{}", app.browser.path, f);
-                        app.pl_new_pl_songs_buffer.clear();
-                        for (t, f) in app.conn.conn.listfiles(&file)?.iter() {
-                            // dir_vec.push((t, f));
-                            if t == "file"
-                                && Path::new(&f).has_extension(&[
-                                    "mp3", "ogg", "flac", "m4a", "wav", "aac", "opus", "ape",
-                                    "wma", "mpc", "aiff", "dff", "mp2", "mka",
-                                ])
-                            {
-                                let full_path = app.conn.get_full_path(f).unwrap_or_default();
-                                let song = app.conn.get_song_with_only_filename(full_path);
-                                if *pl_name == "Current Playlist" {
-                                    app.conn.conn.push(&song)?;
-                                } else if *pl_name == "New Playlist" {
-                                    app.pl_new_pl_songs_buffer.push(song.clone());
-                                    app.inputmode = InputMode::NewPlaylist;
-                                } else {
-                                    app.conn.add_to_playlist(pl_name, &song)?;
-                                }
-                            }
+                        // The whole tree, subdirectories included
+                        let path = format!("{}/{}", app.browser.path, f);
+                        let path = path.strip_prefix("./").unwrap_or(&path).to_string();
+                        let sources = vec![Source::Directory(path)];
+                        if *pl_name == "Current Playlist" {
+                            app.adder.add(Destination::Queue, sources);
+                        } else if *pl_name == "New Playlist" {
+                            app.pl_new_pl_songs_buffer = sources;
+                            app.inputmode = InputMode::NewPlaylist;
+                        } else {
+                            app.adder
+                                .add(Destination::Playlist(pl_name.to_string()), sources);
                         }
                     }
                 }
 
+                SelectedTab::Library => {
+                    let sources: Vec<Source> = app
+                        .library
+                        .source_at(app.library.selected)
+                        .into_iter()
+                        .collect();
+                    if *pl_name == "Current Playlist" {
+                        app.adder.add(Destination::Queue, sources);
+                    } else if *pl_name == "New Playlist" {
+                        app.pl_new_pl_songs_buffer = sources;
+                        app.inputmode = InputMode::NewPlaylist;
+                    } else {
+                        app.adder
+                            .add(Destination::Playlist(pl_name.to_string()), sources);
+                    }
+                }
+
+                SelectedTab::Search => {
+                    let Some(song) = app.search_results.list.get(app.search_results.index) else {
+                        app.playlist_popup = false;
+                        return Ok(());
+                    };
+
+                    if *pl_name == "Current Playlist" {
+                        app.conn.client()?.push(song)?;
+                        app.update_queue()?;
+                    } else if *pl_name == "New Playlist" {
+                        app.pl_new_pl_songs_buffer = vec![Source::File(song.file.clone())];
+                        app.inputmode = InputMode::NewPlaylist;
+                    } else {
+                        app.conn.add_to_playlist(pl_name, song)?;
+                    }
+                }
+
                 SelectedTab::Playlists => {
                     let playlist_name = app.pl_list.get_item_at_current_index();
                     if *pl_name == "Current Playlist" {
                         app.conn.load_playlist(playlist_name)?;
-                        app.update_queue();
+                        app.update_queue()?;
                     } else if *pl_name == "New Playlist" {
                         app.inputmode = InputMode::NewPlaylist;
                     } else {
-                        let songs = app.conn.conn.playlist(playlist_name)?;
+                        // Songs which left the database are refused, they are
+                        // counted and the playlist doctor can deal with them
+                        let songs = app.conn.client()?.playlist(playlist_name)?;
+                        let mut missing = 0;
                         for song in songs {
-                            // We ignore the Err() since there could be songs in playlists, which do not exist in the db anymore.
-                            // So instead of panicking, we just ignore if the song does not exists
-                            app.conn.add_to_playlist(pl_name, &song).unwrap_or(());
+                            match app.conn.client()?.pl_push(pl_name.as_str(), &song) {
+                                Ok(()) => {}
+                                Err(mpd::error::Error::Server(_)) => missing += 1,
+                                Err(err) => return Err(err.into()),
+                            }
+                        }
+                        if missing != 0 {
+                            app.error_message = Some(format!(
+                                "{} songs are not in the database anymore, see the playlist doctor",
+                                missing
+                            ));
                         }
                     }
                 }
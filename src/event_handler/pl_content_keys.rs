@@ -0,0 +1,87 @@
+use crate::app::{App, AppResult};
+use crate::event_handler::{handler, keymap::Action};
+
+/// Keys for the songs of the highlighted playlist, bound like in the queue
+pub fn handle_pl_content_keys(action: Action, app: &mut App) -> AppResult<()> {
+    let Some(name) = app.pl_list.list.get(app.pl_list.index).cloned() else {
+        app.pl_content_focus = false;
+        return Ok(());
+    };
+    let index = app.pl_songs.index;
+    let song = app.pl_songs.list.get(index).cloned();
+
+    match (action, song) {
+        (Action::Back, _) => app.pl_content_focus = false,
+
+        (Action::Down, _) => app.pl_songs.next(),
+        (Action::Up, _) => app.pl_songs.prev(),
+        (Action::Top, _) => app.pl_songs.index = 0,
+        (Action::Bottom, _) => app.pl_songs.index = app.pl_songs.list.len().saturating_sub(1),
+
+        // Play the highlighted song
+        (Action::Select, Some(song)) => {
+            app.play_song(&song)?;
+            app.conn.update_status()?;
+        }
+
+        // Add the highlighted song to the queue, or remove it from there
+        (Action::ToggleInQueue, Some(song)) => {
+            app.toggle_in_queue(&song)?;
+            app.update_queue()?;
+            app.pl_songs.next();
+        }
+
+        (Action::Add, Some(song)) => {
+            app.conn.client()?.push(&song)?;
+            app.update_queue()?;
+        }
+
+        (Action::AddAndPlay, Some(song)) => {
+            app.conn.push(&song)?;
+            app.update_queue()?;
+            app.conn.update_status()?;
+        }
+
+        // Remove the highlighted song from the playlist
+        (Action::DeleteFromQueue, Some(_)) => {
+            app.conn.client()?.pl_delete(&name, index as u32)?;
+            app.pl_contents.remove(&name);
+            app.update_playlist_songs()?;
+        }
+
+        // Swap the highlighted song with the next or previous one
+        (Action::MoveDown, Some(_)) if index + 1 < app.pl_songs.list.len() => {
+            app.conn
+                .client()?
+                .pl_shift(&name, index as u32, index as u32 + 1)?;
+            app.pl_songs.index += 1;
+            app.pl_contents.remove(&name);
+            app.update_playlist_songs()?;
+        }
+
+        (Action::MoveUp, Some(_)) if index > 0 => {
+            app.conn
+                .client()?
+                .pl_shift(&name, index as u32, index as u32 - 1)?;
+            app.pl_songs.index -= 1;
+            app.pl_contents.remove(&name);
+            app.update_playlist_songs()?;
+        }
+
+        (
+            Action::Select
+            | Action::ToggleInQueue
+            | Action::Add
+            | Action::AddAndPlay
+            | Action::DeleteFromQueue
+            | Action::MoveDown
+            | Action::MoveUp,
+            _,
+        ) => {}
+
+        // Everything else is not about the playlist
+        (action, _) => handler::handle_action(action, app)?,
+    }
+
+    Ok(())
+}
@@ -1,43 +1,52 @@
 use crate::{
-    app::{App, AppResult},
+    app::{App, AppResult, PlaylistPrompt},
+    event_handler::keymap::{Action, Context},
     ui::InputMode,
 };
 use crossterm::event::{KeyCode, KeyEvent};
 
 pub fn handle_pl_rename_keys(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
-    match key_event.code {
-        KeyCode::Esc => {
+    match app.config.keys.get(Context::Input, key_event.into()) {
+        Some(Action::Cancel) => {
             app.pl_newname_input.clear();
             app.reset_cursor();
             app.inputmode = InputMode::Normal;
         }
-        KeyCode::Char(to_insert) => {
-            app.enter_char(to_insert);
-        }
-        KeyCode::Enter => {
-            app.conn.conn.pl_rename(
-                app.pl_list.get_item_at_current_index(),
-                &app.pl_newname_input,
-            )?;
-            app.pl_list.list = App::get_playlist(&mut app.conn.conn)?;
-            app.pl_newname_input.clear();
+        Some(Action::Confirm) => {
+            let name = std::mem::take(&mut app.pl_newname_input);
             app.reset_cursor();
             app.inputmode = InputMode::Normal;
+            match app.pl_name_prompt.clone() {
+                PlaylistPrompt::Rename => {
+                    app.conn
+                        .client()?
+                        .pl_rename(app.pl_list.get_item_at_current_index(), &name)?;
+                    app.update_playlists()?;
+                }
+                PlaylistPrompt::SaveQueue => app.save_queue(&name)?,
+                PlaylistPrompt::Duplicate(source) => app.duplicate_playlist(&source, &name)?,
+                PlaylistPrompt::Export(source) => app.export_playlist(source.as_deref(), &name)?,
+                PlaylistPrompt::Import => app.import_playlist(&name)?,
+            }
         }
 
-        KeyCode::Backspace => {
+        Some(Action::DeleteChar) => {
             app.delete_char();
         }
 
-        KeyCode::Left => {
+        Some(Action::CursorLeft) => {
             app.move_cursor_left();
         }
 
-        KeyCode::Right => {
+        Some(Action::CursorRight) => {
             app.move_cursor_right();
         }
 
-        _ => {}
+        _ => {
+            if let KeyCode::Char(to_insert) = key_event.code {
+                app.enter_char(to_insert);
+            }
+        }
     }
     Ok(())
 }
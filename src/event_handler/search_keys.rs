@@ -1,110 +1,55 @@
 use crate::{
-    app::{App, AppResult, SelectedTab},
+    app::{App, AppResult},
+    event_handler::keymap::{Action, Context},
     ui::InputMode,
 };
 use crossterm::event::{KeyCode, KeyEvent};
-use rust_fuzzy_search::{self, fuzzy_search_sorted};
 
 pub fn handle_search_keys(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
-    match app.selected_tab {
-        SelectedTab::DirectoryBrowser => {
-            let list: Vec<&str> = app
-                .browser
-                .filetree
-                .iter()
-                .map(|(_, f)| f.as_str())
-                .collect::<Vec<&str>>();
-
-            let res: Vec<(&str, f32)> = fuzzy_search_sorted(&app.search_input, &list);
-            let res = res.iter().map(|(x, _)| *x).collect::<Vec<&str>>();
-
-            for (i, (_, item)) in app.browser.filetree.iter().enumerate() {
-                if item.contains(res.first().unwrap()) {
-                    app.browser.selected = i;
-                }
-            }
-        }
-
-        SelectedTab::Queue => {
-            let list: Vec<&str> = app
-                .queue_list
-                .list
-                .iter()
-                .map(|f| f.file.as_str())
-                .collect::<Vec<&str>>();
-            let res: Vec<(&str, f32)> = fuzzy_search_sorted(&app.search_input, &list);
-            let res = res.iter().map(|(x, _)| *x).collect::<Vec<&str>>();
-
-            for (i, item) in app.queue_list.list.iter().enumerate() {
-                if item.file.contains(res.first().unwrap()) {
-                    app.queue_list.index = i;
-                }
-            }
-        }
-
-        SelectedTab::Playlists => {
-            let list: Vec<&str> = app
-                .pl_list
-                .list
-                .iter()
-                .map(|f| f.as_str())
-                .collect::<Vec<&str>>();
-            let res: Vec<(&str, f32)> = fuzzy_search_sorted(&app.search_input, &list);
-            let res = res.iter().map(|(x, _)| *x).collect::<Vec<&str>>();
-
-            for (i, item) in app.pl_list.list.iter().enumerate() {
-                if item.contains(res.first().unwrap()) {
-                    app.pl_list.index = i;
-                }
-            }
-        }
-    }
-
-    // Keybind for searching
-    //
     // Keybinds for when the search prompt is visible
-    match key_event.code {
-        KeyCode::Esc => {
+    match app.config.keys.get(Context::Input, key_event.into()) {
+        Some(Action::Cancel) => {
+            app.search_input.clear();
+            app.reset_cursor();
             app.inputmode = InputMode::Normal;
+            return Ok(());
         }
-        KeyCode::Char(to_insert) => {
-            app.enter_char(to_insert);
-        }
-        KeyCode::Enter => {
-            let list: Vec<&str> = app
-                .browser
-                .filetree
-                .iter()
-                .map(|(_, f)| f.as_str())
-                .collect::<Vec<&str>>();
-
-            let res: Vec<(&str, f32)> = fuzzy_search_sorted(&app.search_input, &list);
-            let (res, _) = res.first().unwrap();
-
-            for (i, (_, item)) in app.browser.filetree.iter().enumerate() {
-                if item.contains(res) {
-                    app.browser.selected = i;
-                }
-            }
-
+        Some(Action::Confirm) => {
+            app.confirm_search();
             app.search_input.clear();
             app.reset_cursor();
             app.inputmode = InputMode::Normal;
+            return Ok(());
         }
 
-        KeyCode::Backspace => {
+        Some(Action::CycleSearchMode) => {
+            app.search_mode = app.search_mode.next();
+        }
+
+        Some(Action::ToggleCaseSensitive) => {
+            app.search_case_sensitive = !app.search_case_sensitive;
+        }
+
+        Some(Action::DeleteChar) => {
             app.delete_char();
         }
 
-        KeyCode::Left => {
+        Some(Action::CursorLeft) => {
             app.move_cursor_left();
         }
 
-        KeyCode::Right => {
+        Some(Action::CursorRight) => {
             app.move_cursor_right();
         }
 
-        _ => {}
+        _ => {
+            if let KeyCode::Char(to_insert) = key_event.code {
+                app.enter_char(to_insert);
+            }
+        }
     }
+
+    // Highlight the closest row while typing
+    app.search_as_you_type();
     Ok(())
 }
@@ -1,6 +1,21 @@
 /// Handle mpd connection
 pub mod connection;
 
+/// MPD server address
+pub mod address;
+
+/// Command line arguments
+pub mod cli;
+
+/// Configuration file
+pub mod config;
+
+/// Colors of the user interface
+pub mod theme;
+
+/// Table columns
+pub mod columns;
+
 /// Widget renderer
 pub mod ui;
 
@@ -13,6 +28,24 @@ pub mod list;
 /// File Browser
 pub mod browser;
 
+/// Library browser, by tags
+pub mod library;
+
+/// Database search
+pub mod search;
+
+/// Songs of the stored playlists which left the database
+pub mod doctor;
+
+/// Playlist files on disk: M3U, PLS and XSPF
+pub mod playlist_file;
+
+/// Search prompt of the lists
+pub mod searchable;
+
+/// Adding songs in the background
+pub mod adder;
+
 /// Event Handler/ keymaps
 pub mod event_handler;
 
@@ -0,0 +1,307 @@
+use std::borrow::Cow;
+
+use mpd::{Query, Song, Term};
+
+use crate::{adder::Source, app::AppResult, connection::Connection};
+
+/// What the library browser is listing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LibraryLevel {
+    AlbumArtists,
+    Albums,
+    Tracks,
+    /// Picking the genre the other levels are filtered by
+    Genres,
+    /// Picking the date the other levels are filtered by
+    Dates,
+}
+
+#[derive(Debug)]
+/// struct for working with the library tab, which browses the database by tags
+/// instead of by directories
+pub struct LibraryBrowser {
+    pub level: LibraryLevel,
+    pub items: Vec<String>, // Tag values of the level, or titles of the tracks
+    pub songs: Vec<Song>,   // Tracks of the album, empty on the other levels
+    pub selected: usize,
+    pub album_artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    pub date: Option<String>,
+    prev_selected: Vec<usize>, // Selection of the levels we came from
+    filter_from: LibraryLevel, // Level to go back to when leaving a filter
+}
+
+impl LibraryBrowser {
+    pub fn new() -> LibraryBrowser {
+        LibraryBrowser {
+            level: LibraryLevel::AlbumArtists,
+            items: Vec::new(),
+            songs: Vec::new(),
+            selected: 0,
+            album_artist: None,
+            album: None,
+            genre: None,
+            date: None,
+            prev_selected: Vec::new(),
+            filter_from: LibraryLevel::AlbumArtists,
+        }
+    }
+
+    /// Fetch the items of the current level
+    pub fn update(&mut self, conn: &mut Connection) -> AppResult<()> {
+        let client = conn.client()?;
+        self.songs.clear();
+        self.items = match self.level {
+            LibraryLevel::AlbumArtists => client.list(&tag("AlbumArtist"), &self.query())?,
+            LibraryLevel::Albums => client.list(&tag("Album"), &self.query())?,
+            LibraryLevel::Tracks => {
+                self.songs = client.find(&self.query(), None)?;
+                self.songs.sort_by_key(|song| {
+                    (
+                        tag_number(song, "Disc"),
+                        tag_number(song, "Track"),
+                        song.file.clone(),
+                    )
+                });
+                self.songs
+                    .iter()
+                    .map(|song| song.title.clone().unwrap_or_else(|| song.file.clone()))
+                    .collect()
+            }
+            LibraryLevel::Genres => {
+                let mut genres = vec!["All genres".to_string()];
+                genres.extend(client.list(&tag("Genre"), &Query::new())?);
+                genres
+            }
+            LibraryLevel::Dates => {
+                let mut dates = vec!["All dates".to_string()];
+                dates.extend(client.list(&tag("Date"), &Query::new())?);
+                dates
+            }
+        };
+        self.clamp_selected();
+        Ok(())
+    }
+
+    /// Filters of the current level: the genre and date, and the album artist
+    /// and album picked on the levels above
+    pub fn query(&self) -> Query<'static> {
+        let mut query = Query::new();
+        for (name, value) in self.filters() {
+            query.and(tag(name), value.clone());
+        }
+        query
+    }
+
+    fn filters(&self) -> Vec<(&'static str, &String)> {
+        let filters = [
+            ("Genre", &self.genre),
+            ("Date", &self.date),
+            ("AlbumArtist", &self.album_artist),
+            ("Album", &self.album),
+        ];
+        filters
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value.as_ref()?)))
+            .collect()
+    }
+
+    /// Tags of the songs under the artist or album at the given row, None on
+    /// the tracks and the filters
+    pub fn tags_at(&self, index: usize) -> Option<Vec<(String, String)>> {
+        let item = self.items.get(index)?;
+        let name = match self.level {
+            LibraryLevel::AlbumArtists => "AlbumArtist",
+            LibraryLevel::Albums => "Album",
+            _ => return None,
+        };
+        let mut tags: Vec<(String, String)> = self
+            .filters()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        tags.push((name.to_string(), item.clone()));
+        Some(tags)
+    }
+
+    /// Songs under the item at the given row
+    pub fn songs_at(&self, index: usize, conn: &mut Connection) -> AppResult<Vec<Song>> {
+        match (self.level, self.tags_at(index)) {
+            (_, Some(tags)) => Ok(conn.client()?.find(&tag_query(&tags), None)?),
+            (LibraryLevel::Tracks, None) => {
+                Ok(self.songs.get(index).cloned().into_iter().collect())
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// What adds the item at the given row: its tags, or the file of the track
+    pub fn source_at(&self, index: usize) -> Option<Source> {
+        match self.tags_at(index) {
+            Some(tags) => Some(Source::Tags(tags)),
+            None if self.level == LibraryLevel::Tracks => {
+                Some(Source::File(self.songs.get(index)?.file.clone()))
+            }
+            None => None,
+        }
+    }
+
+    /// Go down into the highlighted artist or album, or apply the highlighted filter
+    ///
+    /// Does nothing on the tracks, they are played by the caller.
+    pub fn handle_enter(&mut self, conn: &mut Connection) -> AppResult<()> {
+        let Some(item) = self.items.get(self.selected).cloned() else {
+            return Ok(());
+        };
+        match self.level {
+            LibraryLevel::AlbumArtists => {
+                self.album_artist = Some(item);
+                self.level = LibraryLevel::Albums;
+            }
+            LibraryLevel::Albums => {
+                self.album = Some(item);
+                self.level = LibraryLevel::Tracks;
+            }
+            LibraryLevel::Tracks => return Ok(()),
+            LibraryLevel::Genres | LibraryLevel::Dates => {
+                // The first item removes the filter
+                let value = (self.selected != 0).then_some(item);
+                if self.level == LibraryLevel::Genres {
+                    self.genre = value;
+                } else {
+                    self.date = value;
+                }
+                return self.reset(conn);
+            }
+        }
+        self.prev_selected.push(self.selected);
+        self.selected = 0;
+        self.update(conn)
+    }
+
+    /// Go back up to the albums or artists, or leave the filter without changing it
+    pub fn handle_go_back(&mut self, conn: &mut Connection) -> AppResult<()> {
+        match self.level {
+            LibraryLevel::AlbumArtists => return Ok(()),
+            LibraryLevel::Albums => {
+                self.album_artist = None;
+                self.level = LibraryLevel::AlbumArtists;
+            }
+            LibraryLevel::Tracks => {
+                self.album = None;
+                self.level = LibraryLevel::Albums;
+            }
+            LibraryLevel::Genres | LibraryLevel::Dates => {
+                self.level = self.filter_from;
+            }
+        }
+        self.selected = self.prev_selected.pop().unwrap_or_default();
+        self.update(conn)
+    }
+
+    /// List the genres or dates to filter by, with the current one highlighted
+    pub fn open_filter(&mut self, level: LibraryLevel, conn: &mut Connection) -> AppResult<()> {
+        if !matches!(self.level, LibraryLevel::Genres | LibraryLevel::Dates) {
+            self.prev_selected.push(self.selected);
+            self.filter_from = self.level;
+        }
+        self.level = level;
+        self.update(conn)?;
+
+        let current = match level {
+            LibraryLevel::Genres => &self.genre,
+            _ => &self.date,
+        };
+        self.selected = current
+            .as_ref()
+            .and_then(|value| self.items.iter().skip(1).position(|item| item == value))
+            .map_or(0, |i| i + 1);
+        Ok(())
+    }
+
+    /// Back to the list of album artists, keeping the filters
+    pub fn reset(&mut self, conn: &mut Connection) -> AppResult<()> {
+        self.level = LibraryLevel::AlbumArtists;
+        self.album_artist = None;
+        self.album = None;
+        self.prev_selected.clear();
+        self.selected = 0;
+        self.update(conn)
+    }
+
+    /// Short description of the level, e.g. `Artist / Album`
+    pub fn location(&self) -> String {
+        match self.level {
+            LibraryLevel::AlbumArtists => "Album Artists".to_string(),
+            LibraryLevel::Albums => display_value(self.album_artist.as_deref()).to_string(),
+            LibraryLevel::Tracks => format!(
+                "{} / {}",
+                display_value(self.album_artist.as_deref()),
+                display_value(self.album.as_deref())
+            ),
+            LibraryLevel::Genres => "Filter by genre".to_string(),
+            LibraryLevel::Dates => "Filter by date".to_string(),
+        }
+    }
+
+    // Go to next item in the list
+    pub fn next(&mut self) {
+        if self.selected < self.items.len().saturating_sub(1) {
+            self.selected += 1;
+        }
+    }
+
+    /// Go to previous item in the list
+    pub fn prev(&mut self) {
+        if self.selected != 0 {
+            self.selected -= 1;
+        }
+    }
+
+    /// Keep the selection inside the list after it shrinks
+    pub fn clamp_selected(&mut self) {
+        if self.selected >= self.items.len() {
+            self.selected = self.items.len().saturating_sub(1);
+        }
+    }
+}
+
+impl Default for LibraryBrowser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Songs without the tag are listed under an empty value
+pub fn display_value(value: Option<&str>) -> &str {
+    match value {
+        Some("") | None => "[unknown]",
+        Some(value) => value,
+    }
+}
+
+fn tag(name: &'static str) -> Term<'static> {
+    Term::Tag(Cow::Borrowed(name))
+}
+
+/// Query for the songs having every tag
+pub fn tag_query(tags: &[(String, String)]) -> Query<'_> {
+    let mut query = Query::new();
+    for (name, value) in tags {
+        query.and(Term::Tag(Cow::Borrowed(name)), value.as_str());
+    }
+    query
+}
+
+/// Number at the start of a tag such as `3/12`, songs without it go last
+fn tag_number(song: &Song, name: &str) -> u32 {
+    song.tags
+        .iter()
+        .find(|(tag, _)| tag.eq_ignore_ascii_case(name))
+        .and_then(|(_, value)| {
+            let digits = value.trim().split(|c: char| !c.is_ascii_digit()).next()?;
+            digits.parse().ok()
+        })
+        .unwrap_or(u32::MAX)
+}
@@ -1,3 +1,5 @@
+use std::{collections::BTreeSet, ops::RangeInclusive};
+
 #[derive(Debug)]
 pub struct ContentList<T> {
     pub list: Vec<T>,
@@ -31,6 +33,13 @@ impl<T> ContentList<T> {
         self.index = 0;
     }
 
+    /// Keep the index inside the list after it shrinks
+    pub fn clamp_index(&mut self) {
+        if self.index >= self.list.len() {
+            self.index = self.list.len().saturating_sub(1);
+        }
+    }
+
     /// Returns the self.list[self.index] item
     pub fn get_item_at_current_index(&mut self) -> &T {
         self.list.get(self.index).unwrap()
@@ -42,3 +51,219 @@ impl<T> Default for ContentList<T> {
         Self::new()
     }
 }
+
+/// Rows of a list left visible by the filter, as indexes into the whole list
+#[derive(Debug, Default)]
+pub struct ListFilter {
+    pub query: String,
+    pub visible: Vec<usize>,
+}
+
+impl ListFilter {
+    pub fn new(query: &str) -> Self {
+        ListFilter {
+            query: query.to_string(),
+            visible: Vec::new(),
+        }
+    }
+
+    /// Keep the rows whose text contains every word of the query, ignoring case
+    pub fn apply<'a>(&mut self, rows: impl Iterator<Item = &'a str>) {
+        let query = self.query.to_lowercase();
+        let words: Vec<&str> = query.split_whitespace().collect();
+        self.visible = rows
+            .enumerate()
+            .filter(|(_, text)| {
+                let text = text.to_lowercase();
+                words.iter().all(|word| text.contains(word))
+            })
+            .map(|(i, _)| i)
+            .collect();
+    }
+
+    /// Position of the row among the visible ones
+    pub fn position(&self, index: usize) -> Option<usize> {
+        self.visible.binary_search(&index).ok()
+    }
+
+    /// First visible row after the given one
+    pub fn next(&self, index: usize) -> Option<usize> {
+        self.visible.iter().copied().find(|i| *i > index)
+    }
+
+    /// Last visible row before the given one
+    pub fn prev(&self, index: usize) -> Option<usize> {
+        self.visible.iter().copied().rev().find(|i| *i < index)
+    }
+
+    /// The row itself if visible, else the closest visible one after or before it
+    pub fn clamp(&self, index: usize) -> Option<usize> {
+        self.visible
+            .iter()
+            .copied()
+            .find(|i| *i >= index)
+            .or_else(|| self.visible.last().copied())
+    }
+}
+
+/// Rows marked for the bulk actions, as indexes into the whole list
+#[derive(Debug, Default)]
+pub struct Selection {
+    pub marked: BTreeSet<usize>, // Rows marked one by one
+    pub anchor: Option<usize>,   // Row the visual mode started on
+}
+
+impl Selection {
+    /// Whether nothing is marked and visual mode is off
+    pub fn is_empty(&self) -> bool {
+        self.marked.is_empty() && self.anchor.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.marked.clear();
+        self.anchor = None;
+    }
+
+    /// Mark the row, or unmark it if it already is
+    pub fn toggle(&mut self, index: usize) {
+        if !self.marked.remove(&index) {
+            self.marked.insert(index);
+        }
+    }
+
+    /// Start selecting the rows between the given one and the highlighted one,
+    /// or stop and keep them marked
+    pub fn toggle_visual(&mut self, cursor: usize) {
+        match self.anchor.take() {
+            Some(anchor) => self.marked.extend(visual_range(anchor, cursor)),
+            None => self.anchor = Some(cursor),
+        }
+    }
+
+    pub fn contains(&self, index: usize, cursor: usize) -> bool {
+        self.marked.contains(&index)
+            || self
+                .anchor
+                .is_some_and(|anchor| visual_range(anchor, cursor).contains(&index))
+    }
+
+    /// Every selected row in order, given the highlighted one
+    pub fn rows(&self, cursor: usize) -> Vec<usize> {
+        let mut rows = self.marked.clone();
+        if let Some(anchor) = self.anchor {
+            rows.extend(visual_range(anchor, cursor));
+        }
+        rows.into_iter().collect()
+    }
+
+    /// Move every selected row, and the start of the visual mode, by `offset` rows
+    pub fn shift(&mut self, offset: isize) {
+        self.marked = self
+            .marked
+            .iter()
+            .map(|i| i.saturating_add_signed(offset))
+            .collect();
+        self.anchor = self.anchor.map(|i| i.saturating_add_signed(offset));
+    }
+
+    /// Forget the rows past the end of the list after it shrinks
+    pub fn truncate(&mut self, len: usize) {
+        self.marked.retain(|i| *i < len);
+        if self.anchor.is_some_and(|anchor| anchor >= len) {
+            self.anchor = None;
+        }
+    }
+}
+
+fn visual_range(anchor: usize, cursor: usize) -> RangeInclusive<usize> {
+    anchor.min(cursor)..=anchor.max(cursor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(query: &str, rows: &[&str]) -> ListFilter {
+        let mut filter = ListFilter::new(query);
+        filter.apply(rows.iter().copied());
+        filter
+    }
+
+    #[test]
+    fn filter_keeps_rows_with_every_word() {
+        let rows = ["Band A - Song One", "Band B - Hit", "band a - Deep Cut"];
+        assert_eq!(filter("band one", &rows).visible, [0]);
+        assert_eq!(filter("BAND", &rows).visible, [0, 1, 2]);
+        assert_eq!(filter("HIT", &rows).visible, [1]);
+        assert_eq!(filter("song cut", &rows).visible, [] as [usize; 0]);
+        assert_eq!(filter("  ", &rows).visible, [0, 1, 2]);
+    }
+
+    #[test]
+    fn filter_moves_between_visible_rows() {
+        let filter = filter("a", &["a", "b", "a", "b", "a"]);
+        assert_eq!(filter.position(2), Some(1));
+        assert_eq!(filter.position(1), None);
+        assert_eq!(filter.next(0), Some(2));
+        assert_eq!(filter.next(1), Some(2));
+        assert_eq!(filter.next(4), None);
+        assert_eq!(filter.prev(4), Some(2));
+        assert_eq!(filter.prev(0), None);
+    }
+
+    #[test]
+    fn filter_clamps_the_index_after_a_refresh() {
+        let mut filter = filter("song", &["song 1", "other", "song 2", "song 3"]);
+        assert_eq!(filter.clamp(1), Some(2));
+        assert_eq!(filter.clamp(3), Some(3));
+
+        // The highlighted row left the list, the closest one is taken
+        filter.apply(["song 1", "other", "song 2"].into_iter());
+        assert_eq!(filter.clamp(3), Some(2));
+        filter.apply(["other"].into_iter());
+        assert_eq!(filter.clamp(0), None);
+    }
+
+    #[test]
+    fn selection_toggles_rows() {
+        let mut selection = Selection::default();
+        assert!(selection.is_empty());
+        selection.toggle(3);
+        selection.toggle(1);
+        assert_eq!(selection.rows(0), [1, 3]);
+        selection.toggle(3);
+        assert_eq!(selection.rows(0), [1]);
+        selection.clear();
+        assert!(selection.is_empty());
+    }
+
+    #[test]
+    fn selection_visual_range_follows_the_cursor() {
+        let mut selection = Selection::default();
+        selection.toggle(0);
+        selection.toggle_visual(5);
+        assert!(!selection.is_empty());
+        assert_eq!(selection.rows(3), [0, 3, 4, 5]);
+        assert_eq!(selection.rows(7), [0, 5, 6, 7]);
+        assert!(selection.contains(4, 3));
+        assert!(!selection.contains(4, 7));
+
+        // Leaving visual mode keeps the rows marked
+        selection.toggle_visual(7);
+        assert_eq!(selection.anchor, None);
+        assert_eq!(selection.rows(0), [0, 5, 6, 7]);
+    }
+
+    #[test]
+    fn selection_follows_the_list() {
+        let mut selection = Selection::default();
+        selection.toggle(2);
+        selection.toggle(4);
+        selection.toggle_visual(6);
+        selection.shift(-1);
+        assert_eq!(selection.rows(5), [1, 3, 5]);
+        selection.truncate(4);
+        assert_eq!(selection.anchor, None);
+        assert_eq!(selection.rows(0), [1, 3]);
+    }
+}
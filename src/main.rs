@@ -1,42 +1,65 @@
 use ratatui::prelude::*;
 use rmptui::app::App;
 use rmptui::app::AppResult;
+use rmptui::cli::Args;
+use rmptui::config::Config;
 use rmptui::event_handler::event::Event;
 use rmptui::event_handler::event::EventHandler;
 use rmptui::event_handler::handler;
 use rmptui::tui;
-use std::env;
 use std::io;
 
 fn main() -> AppResult<()> {
+    let args = Args::parse();
+    let mut config = match Config::load(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        }
+    };
+    if let Some(tab) = args.tab.clone() {
+        config.default_tab = tab;
+    }
+
     // Connection
-    let env_host = env::var("MPD_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
-    let env_port = env::var("MPD_PORT").unwrap_or_else(|_| "6600".to_string());
-    let url = format!("{}:{}", env_host, env_port);
-    let mut app = App::builder(&url)?;
+    let address = args.address()?;
+    let tick_rate = config.tick_rate;
+    let mut app = App::builder(address.clone(), config)?;
 
     // UI
     let backend = CrosstermBackend::new(io::stderr());
     let terminal = Terminal::new(backend)?;
-    let events = EventHandler::new(1000);
+    let mut events = EventHandler::new(tick_rate);
+    events.watch_mpd(&address);
     let mut tui = tui::Tui::new(terminal, events);
     tui.init()?;
 
-    // initial directory read
-    app.browser.update_directory(&mut app.conn)?;
-
     // initially set the queue's highlighted item to the current playing item
-    if let Ok(item) = app.conn.conn.currentsong() {
-        app.queue_list.index = item.unwrap_or_default().place.unwrap_or_default().pos as usize;
+    if let Some(place) = app.conn.current_song.place {
+        app.queue_list.index = place.pos as usize;
     }
 
     while app.running {
         tui.draw(&mut app)?;
-        match tui.events.next()? {
-            Event::Tick => app.tick()?,
-            Event::Key(key_event) => handler::handle_key_events(key_event, &mut app)?,
-            Event::Mouse(mouse_event) => handler::handle_mouse_events(mouse_event, &mut app)?,
-            Event::Resize(_, _) => {}
+        let result = match tui.events.next()? {
+            Event::Tick => app.tick(),
+            Event::Key(key_event) => handler::handle_key_events(key_event, &mut app),
+            Event::Mouse(mouse_event) => handler::handle_mouse_events(mouse_event, &mut app),
+            Event::Resize(_, _) => Ok(()),
+            Event::Mpd(subsystem) => app.handle_mpd_event(subsystem),
+            Event::MpdConnected => {
+                app.conn.retry_now();
+                Ok(())
+            }
+            Event::MpdDisconnected => {
+                app.conn.disconnect();
+                Ok(())
+            }
+        };
+
+        if let Err(err) = result {
+            app.handle_error(err)?;
         }
     }
 
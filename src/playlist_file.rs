@@ -0,0 +1,506 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use mpd::Song;
+
+use crate::app::AppResult;
+
+/// Kinds of playlist files, told apart by their extension
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// `.m3u` or `.m3u8`, written with `#EXTINF` lines
+    M3u,
+    Pls,
+    Xspf,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Result<Format, String> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        match extension.to_lowercase().as_str() {
+            "m3u" | "m3u8" => Ok(Format::M3u),
+            "pls" => Ok(Format::Pls),
+            "xspf" => Ok(Format::Xspf),
+            _ => Err(format!(
+                "unknown playlist format \"{}\", expected m3u, m3u8, pls or xspf",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// A song listed in a playlist file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub location: String,
+    pub title: Option<String>,
+}
+
+/// Expand a leading `~` to the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Write the songs to the file, in the format its extension names
+pub fn export(path: &Path, songs: &[Song]) -> AppResult<()> {
+    let contents = match Format::from_path(path)? {
+        Format::M3u => to_m3u(songs),
+        Format::Pls => to_pls(songs),
+        Format::Xspf => to_xspf(songs),
+    };
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Read the entries of the file, in the format its extension names
+pub fn import(path: &Path) -> AppResult<Vec<Entry>> {
+    let format = Format::from_path(path)?;
+    let contents = fs::read_to_string(path)?;
+    Ok(match format {
+        Format::M3u => parse_m3u(&contents),
+        Format::Pls => parse_pls(&contents),
+        Format::Xspf => parse_xspf(&contents),
+    })
+}
+
+/// "Artist - Title", or whichever of the two the song has
+fn display_title(song: &Song) -> Option<String> {
+    match (&song.artist, &song.title) {
+        (Some(artist), Some(title)) => Some(format!("{} - {}", artist, title)),
+        (None, Some(title)) => Some(title.clone()),
+        (Some(artist), None) => Some(artist.clone()),
+        (None, None) => None,
+    }
+}
+
+fn seconds(song: &Song) -> i64 {
+    song.duration.map_or(-1, |d| d.as_secs() as i64)
+}
+
+fn to_m3u(songs: &[Song]) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    for song in songs {
+        let title = display_title(song).unwrap_or_else(|| song.file.clone());
+        out.push_str(&format!(
+            "#EXTINF:{},{}\n{}\n",
+            seconds(song),
+            title,
+            song.file
+        ));
+    }
+    out
+}
+
+fn to_pls(songs: &[Song]) -> String {
+    let mut out = String::from("[playlist]\n");
+    for (i, song) in songs.iter().enumerate() {
+        let n = i + 1;
+        out.push_str(&format!("File{}={}\n", n, song.file));
+        if let Some(title) = display_title(song) {
+            out.push_str(&format!("Title{}={}\n", n, title));
+        }
+        out.push_str(&format!("Length{}={}\n", n, seconds(song)));
+    }
+    out.push_str(&format!("NumberOfEntries={}\nVersion=2\n", songs.len()));
+    out
+}
+
+fn to_xspf(songs: &[Song]) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n  <trackList>\n",
+    );
+    for song in songs {
+        out.push_str("    <track>\n");
+        out.push_str(&format!(
+            "      <location>{}</location>\n",
+            xml_escape(&percent_encode(&song.file))
+        ));
+        if let Some(title) = &song.title {
+            out.push_str(&format!("      <title>{}</title>\n", xml_escape(title)));
+        }
+        if let Some(artist) = &song.artist {
+            out.push_str(&format!(
+                "      <creator>{}</creator>\n",
+                xml_escape(artist)
+            ));
+        }
+        if let Some(duration) = song.duration {
+            out.push_str(&format!(
+                "      <duration>{}</duration>\n",
+                duration.as_millis()
+            ));
+        }
+        out.push_str("    </track>\n");
+    }
+    out.push_str("  </trackList>\n</playlist>\n");
+    out
+}
+
+fn parse_m3u(contents: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut title = None;
+    for line in contents.lines().map(str::trim) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            title = info.split_once(',').map(|(_, t)| t.trim().to_string());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            entries.push(Entry {
+                location: line.to_string(),
+                title: title.take(),
+            });
+        }
+    }
+    entries
+}
+
+fn parse_pls(contents: &str) -> Vec<Entry> {
+    // Entries are numbered, and their lines may come in any order
+    let mut files: HashMap<u32, String> = HashMap::new();
+    let mut titles: HashMap<u32, String> = HashMap::new();
+    for line in contents.lines().map(str::trim) {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.to_lowercase();
+        if let Some(n) = key.strip_prefix("file").and_then(|n| n.parse().ok()) {
+            files.insert(n, value.to_string());
+        } else if let Some(n) = key.strip_prefix("title").and_then(|n| n.parse().ok()) {
+            titles.insert(n, value.to_string());
+        }
+    }
+
+    let mut numbers: Vec<u32> = files.keys().copied().collect();
+    numbers.sort();
+    numbers
+        .into_iter()
+        .map(|n| Entry {
+            location: files[&n].clone(),
+            title: titles.remove(&n),
+        })
+        .collect()
+}
+
+fn parse_xspf(contents: &str) -> Vec<Entry> {
+    contents
+        .split("<track>")
+        .skip(1)
+        .filter_map(|track| {
+            let track = track.split("</track>").next().unwrap_or_default();
+            let location = xml_element(track, "location")?;
+            Some(Entry {
+                location: percent_decode(&location),
+                title: xml_element(track, "title"),
+            })
+        })
+        .collect()
+}
+
+/// Text of the first `<name>` element
+fn xml_element(xml: &str, name: &str) -> Option<String> {
+    let start = xml.find(&format!("<{}>", name))? + name.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", name))?;
+    Some(xml_unescape(xml[start..end].trim()))
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Escape what cannot appear in a URI, keeping the slashes
+fn percent_encode(path: &str) -> String {
+    let mut out = String::new();
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Finds the songs of the database the entries of a playlist file point at
+pub struct Resolver<'a> {
+    by_name: HashMap<&'a str, Vec<&'a str>>, // Files of the database, by file name
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(files: &'a [String]) -> Self {
+        let mut by_name: HashMap<&str, Vec<&str>> = HashMap::new();
+        for file in files {
+            let name = file.rsplit('/').next().unwrap_or(file);
+            by_name.entry(name).or_default().push(file);
+        }
+        Self { by_name }
+    }
+
+    /// The file of the database, or the stream, the entry stands for
+    ///
+    /// Absolute paths written by other players match the file of the database
+    /// they end with, the longest one when several do.
+    pub fn resolve(&self, entry: &Entry) -> Option<String> {
+        let location = match entry.location.strip_prefix("file://") {
+            Some(path) => percent_decode(path),
+            None if entry.location.contains("://") => return Some(entry.location.clone()),
+            None => entry.location.replace('\\', "/"),
+        };
+        let name = location.rsplit('/').next().unwrap_or(&location);
+        self.by_name
+            .get(name)?
+            .iter()
+            .filter(|file| **file == location || location.ends_with(&format!("/{}", file)))
+            .max_by_key(|file| file.len())
+            .map(|file| file.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn entry(location: &str, title: Option<&str>) -> Entry {
+        Entry {
+            location: location.to_string(),
+            title: title.map(str::to_string),
+        }
+    }
+
+    fn song(file: &str, artist: Option<&str>, title: Option<&str>) -> Song {
+        Song {
+            file: file.to_string(),
+            artist: artist.map(str::to_string),
+            title: title.map(str::to_string),
+            duration: Some(Duration::from_secs(200)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(Format::from_path(Path::new("a.M3U8")), Ok(Format::M3u));
+        assert_eq!(Format::from_path(Path::new("a.m3u")), Ok(Format::M3u));
+        assert_eq!(Format::from_path(Path::new("a.pls")), Ok(Format::Pls));
+        assert_eq!(Format::from_path(Path::new("a.xspf")), Ok(Format::Xspf));
+        assert!(Format::from_path(Path::new("a.txt")).is_err());
+        assert!(Format::from_path(Path::new("playlist")).is_err());
+    }
+
+    #[test]
+    fn m3u() {
+        let contents = "#EXTM3U\r\n\
+                        #EXTINF:200,Band A - Song, One\r\n\
+                        Rock/01 Song One.mp3\r\n\
+                        \r\n\
+                        # a comment\n\
+                        http://radio.example/stream\n";
+        assert_eq!(
+            parse_m3u(contents),
+            [
+                entry("Rock/01 Song One.mp3", Some("Band A - Song, One")),
+                entry("http://radio.example/stream", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn m3u_round_trip() {
+        let songs = [
+            song("Rock/01 Song One.mp3", Some("Band A"), Some("Song One")),
+            song("loose.mp3", None, None),
+        ];
+        assert_eq!(
+            parse_m3u(&to_m3u(&songs)),
+            [
+                entry("Rock/01 Song One.mp3", Some("Band A - Song One")),
+                entry("loose.mp3", Some("loose.mp3")),
+            ]
+        );
+    }
+
+    #[test]
+    fn pls_out_of_order() {
+        let contents = "[playlist]\n\
+                        Title2=Second\n\
+                        File10=ten.mp3\n\
+                        file2=two.mp3\n\
+                        File1=one=1.mp3\n\
+                        Length1=-1\n\
+                        NumberOfEntries=3\n";
+        assert_eq!(
+            parse_pls(contents),
+            [
+                entry("one=1.mp3", None),
+                entry("two.mp3", Some("Second")),
+                entry("ten.mp3", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn pls_round_trip() {
+        let songs = [
+            song("a.mp3", Some("Band A"), Some("Song One")),
+            song("b.mp3", None, None),
+        ];
+        let contents = to_pls(&songs);
+        assert!(contents.contains("NumberOfEntries=2"));
+        assert_eq!(
+            parse_pls(&contents),
+            [
+                entry("a.mp3", Some("Band A - Song One")),
+                entry("b.mp3", None)
+            ]
+        );
+    }
+
+    #[test]
+    fn xspf() {
+        let contents = r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <trackList>
+    <track>
+      <title>Tom &amp; Jerry &lt;live&gt;</title>
+      <location>file:///music/Rock/Tom%20%26%20Jerry.mp3</location>
+    </track>
+    <track><title>No location</title></track>
+    <track>
+      <location>http://radio.example/stream</location>
+    </track>
+  </trackList>
+</playlist>"#;
+        assert_eq!(
+            parse_xspf(contents),
+            [
+                entry(
+                    "file:///music/Rock/Tom & Jerry.mp3",
+                    Some("Tom & Jerry <live>")
+                ),
+                entry("http://radio.example/stream", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn xspf_round_trip() {
+        let songs = [
+            song(
+                "Rock/Tom & Jerry's <Best>/01 \"Hi\" 100%.flac",
+                Some("Tom & Jerry"),
+                Some("\"Hi\" <1>"),
+            ),
+            song("Jazz/Ünïcode ✓.mp3", None, Some("a &amp; b")),
+        ];
+        assert_eq!(
+            parse_xspf(&to_xspf(&songs)),
+            [
+                entry(
+                    "Rock/Tom & Jerry's <Best>/01 \"Hi\" 100%.flac",
+                    Some("\"Hi\" <1>")
+                ),
+                entry("Jazz/Ünïcode ✓.mp3", Some("a &amp; b")),
+            ]
+        );
+    }
+
+    #[test]
+    fn xml_escaping() {
+        let text = "<a href=\"x\">Tom & 'Jerry'</a> &amp;";
+        assert_eq!(xml_unescape(&xml_escape(text)), text);
+        assert_eq!(xml_escape("a&b<c"), "a&amp;b&lt;c");
+    }
+
+    #[test]
+    fn percent_encoding() {
+        assert_eq!(percent_encode("Rock/A b%.mp3"), "Rock/A%20b%25.mp3");
+        assert_eq!(percent_encode("é"), "%C3%A9");
+        assert_eq!(percent_decode("Rock/A%20b%25.mp3"), "Rock/A b%.mp3");
+        assert_eq!(percent_decode("%c3%a9"), "é");
+        // Not an escape, kept as is
+        assert_eq!(percent_decode("100% sure%2"), "100% sure%2");
+        let path = "Jazz/Ünïcode ✓ 50%/#1?.mp3";
+        assert_eq!(percent_decode(&percent_encode(path)), path);
+    }
+
+    #[test]
+    fn resolve() {
+        let files = [
+            "Rock/Band A/01 Song.mp3".to_string(),
+            "Band A/01 Song.mp3".to_string(),
+            "Jazz/Blue.flac".to_string(),
+        ];
+        let resolver = Resolver::new(&files);
+        let resolve = |location: &str| resolver.resolve(&entry(location, None));
+
+        assert_eq!(resolve("Jazz/Blue.flac").as_deref(), Some("Jazz/Blue.flac"));
+        // Absolute paths match the longest file they end with
+        assert_eq!(
+            resolve("/home/me/Music/Rock/Band A/01 Song.mp3").as_deref(),
+            Some("Rock/Band A/01 Song.mp3")
+        );
+        assert_eq!(
+            resolve("/srv/Band A/01 Song.mp3").as_deref(),
+            Some("Band A/01 Song.mp3")
+        );
+        assert_eq!(
+            resolve("file:///home/me/Music/Jazz/Blue.flac").as_deref(),
+            Some("Jazz/Blue.flac")
+        );
+        assert_eq!(
+            resolve("C:\\Music\\Jazz\\Blue.flac").as_deref(),
+            Some("Jazz/Blue.flac")
+        );
+        assert_eq!(
+            resolve("http://radio.example/stream").as_deref(),
+            Some("http://radio.example/stream")
+        );
+        // Same name in another directory, or a file name merely ending the same
+        assert_eq!(resolve("/music/Pop/Blue.flac"), None);
+        assert_eq!(resolve("/music/Jazz/DeepBlue.flac"), None);
+        assert_eq!(resolve("Missing.mp3"), None);
+    }
+}
@@ -0,0 +1,183 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use mpd::{Query, Song, Term};
+
+use crate::address::MpdClient;
+use crate::app::AppResult;
+
+/// Turn the search prompt into MPD filter expressions
+///
+/// `artist:foo album:"bar baz"` looks for tags containing the values, `date=2001`
+/// for equal ones, `genre!=rock` for different ones and `title~^intro` for ones
+/// matching a regular expression. Other words are looked for in any tag. Input
+/// starting with `(` is an MPD filter expression, sent as is.
+pub fn parse_query(input: &str) -> Result<Vec<String>, String> {
+    let input = input.trim();
+    if input.starts_with('(') {
+        return Ok(vec![input.to_string()]);
+    }
+
+    let mut exprs = Vec::new();
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut tag = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !"\":=!~".contains(*c)) {
+            tag.push(c);
+        }
+
+        let operator = match chars.peek() {
+            Some(':') => "contains",
+            Some('=') => "==",
+            Some('~') => "=~",
+            Some('!') => {
+                chars.next();
+                match chars.peek() {
+                    Some('=') => "!=",
+                    Some('~') => "!~",
+                    _ => return Err(format!("expected != or !~ after \"{}\"", tag)),
+                }
+            }
+            // A word, or a quoted sentence, looked for in any tag
+            _ => {
+                let value = if tag.is_empty() {
+                    read_value(&mut chars)?
+                } else {
+                    tag
+                };
+                exprs.push(expression("any", "contains", &value));
+                continue;
+            }
+        };
+        chars.next();
+        if operator == "==" {
+            // `==` works as well as `=`
+            chars.next_if_eq(&'=');
+        }
+
+        if tag.is_empty() {
+            return Err(format!("missing tag name in \"{}\"", input));
+        }
+        if !tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!("invalid tag \"{}\"", tag));
+        }
+        let value = read_value(&mut chars)?;
+        if value.is_empty() {
+            return Err(format!("missing value after \"{}\"", tag));
+        }
+        exprs.push(expression(&tag, operator, &value));
+    }
+
+    if exprs.is_empty() {
+        return Err("nothing to search for".to_string());
+    }
+    Ok(exprs)
+}
+
+/// A word, or a sentence between double quotes
+fn read_value(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut value = String::new();
+    if chars.next_if_eq(&'"').is_none() {
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            value.push(c);
+        }
+        return Ok(value);
+    }
+
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(value),
+            Some('\\') => value.extend(chars.next()),
+            Some(c) => value.push(c),
+            None => return Err(format!("unclosed quote in \"{}", value)),
+        }
+    }
+}
+
+/// `(artist contains 'foo')`
+fn expression(tag: &str, operator: &str, value: &str) -> String {
+    let value = value.replace('\\', "\\\\").replace('\'', "\\'");
+    format!("({} {} '{}')", tag, operator, value)
+}
+
+/// Case-insensitive search of the whole database, every expression has to match
+pub fn search(client: &mut MpdClient, exprs: &[String]) -> AppResult<Vec<Song>> {
+    // The mpd crate only sends filters as tag/value pairs, but MPD takes every
+    // argument starting with "(" as an expression of its own and ANDs them all.
+    // Send the expressions two by two, the last one twice if they are odd.
+    let mut query = Query::new();
+    for pair in exprs.chunks(2) {
+        let first = pair[0].clone();
+        let second = pair[pair.len() - 1].clone();
+        query.and(Term::Tag(first.into()), second);
+    }
+    Ok(client.search(&query, None)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Vec<String> {
+        parse_query(input).unwrap()
+    }
+
+    #[test]
+    fn words() {
+        assert_eq!(
+            parse("  foo   bar "),
+            ["(any contains 'foo')", "(any contains 'bar')"]
+        );
+        assert_eq!(parse("\"foo bar\""), ["(any contains 'foo bar')"]);
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(
+            parse("artist:foo date=2001 genre!=rock title~^intro album!~live"),
+            [
+                "(artist contains 'foo')",
+                "(date == '2001')",
+                "(genre != 'rock')",
+                "(title =~ '^intro')",
+                "(album !~ 'live')",
+            ]
+        );
+        assert_eq!(parse("date==2001"), ["(date == '2001')"]);
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(parse("album:\"bar baz\""), ["(album contains 'bar baz')"]);
+        assert_eq!(
+            parse(r#"title:"say \"hi\"""#),
+            [r#"(title contains 'say "hi"')"#]
+        );
+        assert_eq!(parse("it's"), [r"(any contains 'it\'s')"]);
+        assert_eq!(parse(r"a\b"), [r"(any contains 'a\\b')"]);
+    }
+
+    #[test]
+    fn raw_expression() {
+        assert_eq!(parse(" (artist == 'foo') "), ["(artist == 'foo')"]);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse_query("").is_err());
+        assert!(parse_query("   ").is_err());
+        assert!(parse_query("genre!rock").is_err());
+        assert!(parse_query(":foo").is_err());
+        assert!(parse_query("artist:").is_err());
+        assert!(parse_query("a.b:c").is_err());
+        assert!(parse_query("album:\"bar").is_err());
+    }
+}
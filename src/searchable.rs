@@ -0,0 +1,372 @@
+use mpd::Song;
+use regex::{Regex, RegexBuilder};
+use rust_fuzzy_search::fuzzy_compare;
+
+use crate::{
+    browser::FileBrowser,
+    library::LibraryBrowser,
+    list::{ContentList, ListFilter},
+    utils::find_matches,
+};
+
+/// How the search prompt compares the query with the rows
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SearchMode {
+    /// Characters of the query in the same order, the closest row first
+    #[default]
+    Fuzzy,
+    Substring,
+    Regex,
+}
+
+impl SearchMode {
+    /// Mode the prompt switches to
+    pub fn next(self) -> SearchMode {
+        match self {
+            SearchMode::Fuzzy => SearchMode::Substring,
+            SearchMode::Substring => SearchMode::Regex,
+            SearchMode::Regex => SearchMode::Fuzzy,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SearchMode::Fuzzy => "fuzzy",
+            SearchMode::Substring => "substring",
+            SearchMode::Regex => "regex",
+        }
+    }
+}
+
+/// A query of the search prompt, ready to be compared with the rows
+#[derive(Debug)]
+pub enum Matcher {
+    Fuzzy { query: String, case_sensitive: bool },
+    Substring { query: String, case_sensitive: bool },
+    Regex(Regex),
+}
+
+impl Matcher {
+    /// Fails on an invalid regular expression
+    pub fn new(query: &str, mode: SearchMode, case_sensitive: bool) -> Result<Matcher, String> {
+        let query = query.to_string();
+        match mode {
+            SearchMode::Fuzzy => Ok(Matcher::Fuzzy {
+                query,
+                case_sensitive,
+            }),
+            SearchMode::Substring => Ok(Matcher::Substring {
+                query,
+                case_sensitive,
+            }),
+            SearchMode::Regex => RegexBuilder::new(&query)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .map(Matcher::Regex)
+                .map_err(|e| {
+                    // Only the last line of the message, the others point at the error
+                    let message = e.to_string();
+                    let reason = message.lines().last().unwrap_or_default();
+                    format!("Invalid regex: {}", reason.trim_start_matches("error: "))
+                }),
+        }
+    }
+
+    /// How close the row is to the query, `None` when it does not match
+    pub fn score(&self, text: &str) -> Option<f32> {
+        match self {
+            Matcher::Fuzzy {
+                query,
+                case_sensitive,
+            } => {
+                self.ranges(text).first()?;
+                if *case_sensitive {
+                    Some(fuzzy_compare(query, text))
+                } else {
+                    Some(fuzzy_compare(&query.to_lowercase(), &text.to_lowercase()))
+                }
+            }
+            _ => (!self.ranges(text).is_empty()).then_some(1.0),
+        }
+    }
+
+    /// Byte ranges of the parts of the row matching the query
+    pub fn ranges(&self, text: &str) -> Vec<(usize, usize)> {
+        match self {
+            Matcher::Fuzzy {
+                query,
+                case_sensitive,
+            } => fuzzy_ranges(text, query, *case_sensitive),
+            Matcher::Substring {
+                query,
+                case_sensitive: false,
+            } => find_matches(text, query),
+            Matcher::Substring {
+                query,
+                case_sensitive: true,
+            } => {
+                if query.is_empty() {
+                    return Vec::new();
+                }
+                text.match_indices(query.as_str())
+                    .map(|(i, m)| (i, i + m.len()))
+                    .collect()
+            }
+            Matcher::Regex(regex) => regex
+                .find_iter(text)
+                .filter(|m| !m.is_empty())
+                .map(|m| (m.start(), m.end()))
+                .collect(),
+        }
+    }
+}
+
+/// Ranges of the characters of the query found in order in the text, empty
+/// when some are missing
+fn fuzzy_ranges(text: &str, query: &str, case_sensitive: bool) -> Vec<(usize, usize)> {
+    let same = |a: char, b: char| {
+        if case_sensitive {
+            a == b
+        } else {
+            a.to_lowercase().eq(b.to_lowercase())
+        }
+    };
+
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    let mut query = query.chars().peekable();
+    for (i, c) in text.char_indices() {
+        let Some(&wanted) = query.peek() else {
+            break;
+        };
+        if !same(c, wanted) {
+            continue;
+        }
+        query.next();
+        let end = i + c.len_utf8();
+        match ranges.last_mut() {
+            // Characters next to each other make a single range
+            Some((_, last_end)) if *last_end == i => *last_end = end,
+            _ => ranges.push((i, end)),
+        }
+    }
+
+    if query.peek().is_some() {
+        return Vec::new();
+    }
+    ranges
+}
+
+/// A list the search prompt and `n`/`N` move the highlight in
+pub trait Searchable {
+    /// Text of every row the search looks into
+    fn search_texts(&self) -> Vec<String>;
+
+    fn row_count(&self) -> usize;
+
+    fn selected(&self) -> usize;
+
+    fn select(&mut self, index: usize);
+
+    /// Matching rows the filter shows, with how close they are to the query
+    fn matching_rows(&self, matcher: &Matcher, filter: Option<&ListFilter>) -> Vec<(usize, f32)> {
+        self.search_texts()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| filter.is_none_or(|filter| filter.position(*i).is_some()))
+            .filter_map(|(i, text)| Some((i, matcher.score(text)?)))
+            .collect()
+    }
+
+    /// Closest matching row, the first one among equally close rows
+    fn best_match(&self, matcher: &Matcher, filter: Option<&ListFilter>) -> Option<usize> {
+        let mut best: Option<(usize, f32)> = None;
+        for (i, score) in self.matching_rows(matcher, filter) {
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((i, score));
+            }
+        }
+        best.map(|(i, _)| i)
+    }
+}
+
+impl Searchable for ContentList<Song> {
+    fn search_texts(&self) -> Vec<String> {
+        self.list.iter().map(song_text).collect()
+    }
+
+    fn row_count(&self) -> usize {
+        self.list.len()
+    }
+
+    fn selected(&self) -> usize {
+        self.index
+    }
+
+    fn select(&mut self, index: usize) {
+        self.index = index;
+    }
+}
+
+impl Searchable for ContentList<String> {
+    fn search_texts(&self) -> Vec<String> {
+        self.list.clone()
+    }
+
+    fn row_count(&self) -> usize {
+        self.list.len()
+    }
+
+    fn selected(&self) -> usize {
+        self.index
+    }
+
+    fn select(&mut self, index: usize) {
+        self.index = index;
+    }
+}
+
+impl Searchable for FileBrowser {
+    /// Names of the directories, tags and path of the songs
+    fn search_texts(&self) -> Vec<String> {
+        self.filetree
+            .iter()
+            .zip(self.songs.iter())
+            .map(|((t, name), song)| {
+                if t == "file" {
+                    song_text(song)
+                } else {
+                    name.clone()
+                }
+            })
+            .collect()
+    }
+
+    fn row_count(&self) -> usize {
+        self.filetree.len()
+    }
+
+    fn selected(&self) -> usize {
+        self.selected
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index;
+    }
+}
+
+impl Searchable for LibraryBrowser {
+    fn search_texts(&self) -> Vec<String> {
+        self.items.clone()
+    }
+
+    fn row_count(&self) -> usize {
+        self.items.len()
+    }
+
+    fn selected(&self) -> usize {
+        self.selected
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index;
+    }
+}
+
+/// Text of a song the filters and search look into: title, artist, album and file
+pub fn song_text(song: &Song) -> String {
+    let album = song
+        .tags
+        .iter()
+        .find(|(tag, _)| tag == "Album")
+        .map(|(_, album)| album.as_str());
+    [
+        song.title.as_deref(),
+        song.artist.as_deref(),
+        album,
+        Some(song.file.as_str()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<&str>>()
+    .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(query: &str, mode: SearchMode, case_sensitive: bool) -> Matcher {
+        Matcher::new(query, mode, case_sensitive).unwrap()
+    }
+
+    #[test]
+    fn substring_modes() {
+        let insensitive = matcher("song", SearchMode::Substring, false);
+        assert_eq!(insensitive.ranges("Song of a song"), [(0, 4), (10, 14)]);
+        assert_eq!(insensitive.score("Hit"), None);
+
+        let sensitive = matcher("song", SearchMode::Substring, true);
+        assert_eq!(sensitive.ranges("Song of a song"), [(10, 14)]);
+        assert_eq!(sensitive.score("SONG"), None);
+        assert_eq!(matcher("", SearchMode::Substring, true).ranges("a"), []);
+    }
+
+    #[test]
+    fn regex_modes() {
+        let insensitive = matcher("^s.ng", SearchMode::Regex, false);
+        assert_eq!(insensitive.ranges("Song One"), [(0, 4)]);
+        assert_eq!(insensitive.score("One Song"), None);
+
+        let sensitive = matcher("^s.ng", SearchMode::Regex, true);
+        assert_eq!(sensitive.ranges("Song One"), []);
+        assert_eq!(sensitive.score("sing"), Some(1.0));
+
+        // Empty matches would highlight nothing
+        assert_eq!(matcher("x*", SearchMode::Regex, false).score("abc"), None);
+    }
+
+    #[test]
+    fn invalid_regex() {
+        let err = Matcher::new("(unclosed", SearchMode::Regex, false).unwrap_err();
+        assert!(err.starts_with("Invalid regex: "), "{}", err);
+        assert_eq!(err.lines().count(), 1);
+        // The other modes take the same query literally
+        assert!(Matcher::new("(unclosed", SearchMode::Substring, false).is_ok());
+    }
+
+    #[test]
+    fn fuzzy_modes() {
+        let insensitive = matcher("sgo", SearchMode::Fuzzy, false);
+        assert_eq!(insensitive.ranges("Song One"), [(0, 1), (3, 4), (5, 6)]);
+        assert_eq!(insensitive.ranges("One Song"), []);
+        assert_eq!(
+            matcher("éa", SearchMode::Fuzzy, false).ranges("Été a"),
+            [(0, 2), (6, 7)]
+        );
+
+        let sensitive = matcher("SO", SearchMode::Fuzzy, true);
+        assert_eq!(sensitive.score("Song one"), None);
+        assert!(sensitive.score("SOng").is_some());
+    }
+
+    #[test]
+    fn best_match_prefers_the_closest_row() {
+        let mut list = ContentList::new();
+        list.list = ["Blue Note", "Song of Two Towns", "Song Two", "Song"]
+            .map(String::from)
+            .to_vec();
+        let fuzzy = matcher("song two", SearchMode::Fuzzy, false);
+        assert_eq!(list.best_match(&fuzzy, None), Some(2));
+
+        // Equally close rows, the first one wins
+        let substring = matcher("song", SearchMode::Substring, false);
+        assert_eq!(list.best_match(&substring, None), Some(1));
+
+        // Rows hidden by the filter are skipped
+        let o = matcher("o", SearchMode::Substring, false);
+        assert_eq!(list.best_match(&o, None), Some(0));
+        let mut filter = ListFilter::new("song");
+        filter.apply(list.list.iter().map(String::as_str));
+        assert_eq!(list.best_match(&o, Some(&filter)), Some(1));
+    }
+}
@@ -0,0 +1,216 @@
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "HashMap<String, String>")]
+/// Styles used by every widget of the user interface
+pub struct Theme {
+    pub artist: Style,
+    pub track: Style,
+    pub title: Style,
+    pub album: Style,
+    pub time: Style,
+    /// Column names of the tables
+    pub header: Style,
+    /// Highlighted row of the lists and tables
+    pub highlight: Style,
+    /// Names of the views and prompts
+    pub block_title: Style,
+    /// Song count and volume
+    pub info: Style,
+    /// Muted volume
+    pub muted: Style,
+    /// Songs of the directory browser which are already in the queue
+    pub in_queue: Style,
+    /// Player state shown before the song
+    pub state: Style,
+    /// Connection banner and errors
+    pub error: Style,
+    /// Song shown in the progress bar
+    pub now_playing: Style,
+    /// Elapsed and total time in the progress bar
+    pub elapsed: Style,
+    pub progress_bar: Style,
+    /// Text matching the search
+    pub search_match: Style,
+    /// Rows marked for the bulk actions
+    pub selected: Style,
+}
+
+impl Theme {
+    /// Colors for terminals with a dark background
+    pub fn dark() -> Self {
+        Theme {
+            artist: Style::new().fg(Color::Cyan),
+            track: Style::new().fg(Color::Green),
+            title: Style::new(),
+            album: Style::new().fg(Color::Cyan),
+            time: Style::new().fg(Color::Magenta),
+            header: Style::new().add_modifier(Modifier::BOLD),
+            highlight: Style::new()
+                .fg(Color::Cyan)
+                .bg(Color::Black)
+                .add_modifier(Modifier::REVERSED),
+            block_title: Style::new().fg(Color::Green).add_modifier(Modifier::BOLD),
+            info: Style::new().fg(Color::Green),
+            muted: Style::new().fg(Color::Red),
+            in_queue: Style::new().add_modifier(Modifier::BOLD),
+            state: Style::new().fg(Color::Red).add_modifier(Modifier::BOLD),
+            error: Style::new().fg(Color::Red).add_modifier(Modifier::BOLD),
+            now_playing: Style::new().fg(Color::Green),
+            elapsed: Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            progress_bar: Style::new()
+                .fg(Color::Blue)
+                .bg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            search_match: Style::new()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            selected: Style::new()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::ITALIC),
+        }
+    }
+
+    /// Colors for terminals with a light background, which leave the background alone
+    pub fn light() -> Self {
+        Theme {
+            artist: Style::new().fg(Color::Blue),
+            album: Style::new().fg(Color::Blue),
+            highlight: Style::new()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::REVERSED | Modifier::BOLD),
+            progress_bar: Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD),
+            search_match: Style::new()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            selected: Style::new().bg(Color::Gray).add_modifier(Modifier::ITALIC),
+            ..Theme::dark()
+        }
+    }
+
+    /// No colors at all, only bold and reversed text
+    pub fn monochrome() -> Self {
+        Theme::dark().without_colors()
+    }
+
+    /// Built-in theme by name
+    pub fn preset(name: &str) -> Result<Self, String> {
+        match name {
+            "dark" => Ok(Theme::dark()),
+            "light" => Ok(Theme::light()),
+            "monochrome" => Ok(Theme::monochrome()),
+            _ => Err(format!(
+                "unknown theme preset \"{}\", expected dark, light or monochrome",
+                name
+            )),
+        }
+    }
+
+    /// Keep the text modifiers and drop every color, for NO_COLOR
+    pub fn without_colors(mut self) -> Self {
+        for (_, style) in self.styles_mut() {
+            *style = Style::new()
+                .add_modifier(style.add_modifier)
+                .remove_modifier(style.sub_modifier);
+        }
+        self
+    }
+
+    /// Style by its name in the configuration file
+    pub fn style(&self, name: &str) -> Option<Style> {
+        self.clone()
+            .styles_mut()
+            .into_iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, style)| *style)
+    }
+
+    /// Every style with its name in the configuration file
+    fn styles_mut(&mut self) -> [(&'static str, &mut Style); 18] {
+        [
+            ("artist", &mut self.artist),
+            ("track", &mut self.track),
+            ("title", &mut self.title),
+            ("album", &mut self.album),
+            ("time", &mut self.time),
+            ("header", &mut self.header),
+            ("highlight", &mut self.highlight),
+            ("block_title", &mut self.block_title),
+            ("info", &mut self.info),
+            ("muted", &mut self.muted),
+            ("in_queue", &mut self.in_queue),
+            ("state", &mut self.state),
+            ("error", &mut self.error),
+            ("now_playing", &mut self.now_playing),
+            ("elapsed", &mut self.elapsed),
+            ("progress_bar", &mut self.progress_bar),
+            ("search_match", &mut self.search_match),
+            ("selected", &mut self.selected),
+        ]
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+/// `[theme]` table of the configuration file: a `preset` and styles overriding
+/// some of its own
+impl TryFrom<HashMap<String, String>> for Theme {
+    type Error = String;
+
+    fn try_from(mut table: HashMap<String, String>) -> Result<Self, Self::Error> {
+        let mut theme = match table.remove("preset") {
+            Some(name) => Theme::preset(&name)?,
+            None => Theme::default(),
+        };
+
+        for (name, value) in table {
+            let style = theme
+                .styles_mut()
+                .into_iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, style)| style)
+                .ok_or_else(|| format!("unknown theme element \"{}\"", name))?;
+            *style = parse_style(&value)?;
+        }
+
+        Ok(theme)
+    }
+}
+
+/// Parse a style such as `bold cyan on black`
+pub fn parse_style(s: &str) -> Result<Style, String> {
+    let mut style = Style::new();
+    let mut words = s.split_whitespace();
+
+    while let Some(word) = words.next() {
+        style = match word.to_lowercase().as_str() {
+            "bold" => style.add_modifier(Modifier::BOLD),
+            "dim" => style.add_modifier(Modifier::DIM),
+            "italic" => style.add_modifier(Modifier::ITALIC),
+            "underlined" => style.add_modifier(Modifier::UNDERLINED),
+            "reversed" => style.add_modifier(Modifier::REVERSED),
+            "crossed_out" => style.add_modifier(Modifier::CROSSED_OUT),
+            "default" => style,
+            "on" => {
+                let color = words
+                    .next()
+                    .ok_or_else(|| format!("missing background color in \"{}\"", s))?;
+                style.bg(parse_color(color)?)
+            }
+            _ => style.fg(parse_color(word)?),
+        };
+    }
+
+    Ok(style)
+}
+
+fn parse_color(s: &str) -> Result<Color, String> {
+    s.parse()
+        .map_err(|_| format!("unknown color or modifier \"{}\"", s))
+}
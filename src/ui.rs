@@ -1,9 +1,16 @@
-use std::time::Duration;
+use std::time::Instant;
 
 use crate::{
-    app::{App, SelectedTab},
-    connection::VolumeStatus,
+    app::{App, PlaylistPrompt, SelectedTab},
+    columns::Column,
+    connection::{ConnectionState, VolumeStatus},
+    event_handler::keymap::{Action, Context, Keymap},
+    library::{display_value, LibraryLevel},
+    list::ListFilter,
+    searchable::Matcher,
+    theme::Theme,
 };
+use mpd::Song;
 use ratatui::{
     prelude::*,
     widgets::{block::Title, *},
@@ -15,6 +22,9 @@ pub enum InputMode {
     Normal,
     PlaylistRename,
     NewPlaylist,
+    DatabaseSearch,
+    Filter,
+    Confirm,
 }
 
 /// Renders the user interface widgets
@@ -29,9 +39,14 @@ pub fn render(app: &mut App, frame: &mut Frame) {
         SelectedTab::Queue => draw_queue(frame, app, layout[0]),
         SelectedTab::Playlists => draw_playlist_viewer(frame, app, layout[0]),
         SelectedTab::DirectoryBrowser => draw_directory_browser(frame, app, layout[0]),
+        SelectedTab::Library => draw_library(frame, app, layout[0]),
+        SelectedTab::Search => draw_search_results(frame, app, layout[0]),
     }
 
     match app.inputmode {
+        InputMode::Normal if app.conn.conn_state != ConnectionState::Connected => {
+            draw_connection_banner(frame, app, layout[1]);
+        }
         InputMode::Normal => {
             draw_progress_bar(frame, app, layout[1]);
         }
@@ -44,215 +59,457 @@ pub fn render(app: &mut App, frame: &mut Frame) {
         InputMode::NewPlaylist => {
             draw_new_playlist(frame, app, layout[1]);
         }
+        InputMode::DatabaseSearch => {
+            draw_database_search_bar(frame, app, layout[1]);
+        }
+        InputMode::Filter => {
+            draw_filter_bar(frame, app, layout[1]);
+        }
+        InputMode::Confirm => {
+            draw_confirmation(frame, app, layout[1]);
+        }
     }
 
     if app.playlist_popup {
         draw_add_to_playlist(frame, app, layout[0]);
     }
+
+    if app.doctor_popup {
+        draw_playlist_doctor(frame, app, layout[0]);
+    }
 }
 
 /// Draws the directory
 fn draw_directory_browser(frame: &mut Frame, app: &mut App, size: Rect) {
+    let theme = &app.config.theme;
+    let columns = &app.config.columns;
+    let styles = column_styles(columns, theme);
     let total_songs = app.conn.stats.songs.to_string();
+    let matcher = search_matcher(app);
+
+    let filter = app.browser_filter.as_ref();
+    let rows = app
+        .browser
+        .filetree
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| is_visible(filter, *i))
+        .map(|(i, (t, s))| {
+            if t == "file" {
+                let song = app.browser.songs.get(i).unwrap();
+
+                let mut status: bool = false;
+                for sn in app.queue_list.list.iter() {
+                    let file = sn.file.split('/').next_back().unwrap_or_default();
+                    if file.eq(s) {
+                        status = true;
+                    }
+                }
 
-    let rows = app.browser.filetree.iter().enumerate().map(|(i, (t, s))| {
-        if t == "file" {
-            let song = app.browser.songs.get(i).unwrap().clone();
-
-            // metadata
-            let title = song.clone().title.unwrap_or_else(|| song.clone().file);
-            let artist = song.clone().artist.unwrap_or_default().cyan();
-            let album = song
-                .tags
-                .iter()
-                .filter(|(x, _)| x == "Album")
-                .map(|(_, l)| l.clone())
-                .collect::<Vec<String>>()
-                .join("");
-
-            let track = song
-                .tags
-                .iter()
-                .filter(|(x, _)| x == "Track")
-                .map(|(_, l)| l.clone())
-                .collect::<Vec<String>>()
-                .join("");
-
-            let time =
-                App::format_time(song.clone().duration.unwrap_or_else(|| Duration::new(0, 0)));
-
-            let mut status: bool = false;
-            for sn in app.queue_list.list.iter() {
-                let file = sn.file.split('/').last().unwrap_or_default();
-                if file.eq(s) {
-                    status = true;
+                let row = Row::new(song_cells(
+                    song,
+                    columns,
+                    &styles,
+                    "",
+                    matcher.as_ref(),
+                    theme,
+                ));
+
+                if status {
+                    row.style(row_style(app, i, theme.in_queue))
+                } else {
+                    row.style(row_style(app, i, Style::default()))
                 }
+            } else {
+                let name = format!("[{}]", *s);
+                Row::new(vec![Cell::from(highlight(
+                    name,
+                    matcher.as_ref(),
+                    theme.search_match,
+                ))])
+                .style(row_style(app, i, Style::default()))
             }
+        });
 
-            let row = Row::new(vec![
-                Cell::from(artist),
-                Cell::from(track.green()),
-                Cell::from(title),
-                Cell::from(album.cyan()),
-                Cell::from(time.to_string().magenta()),
-            ]);
+    let header = column_header(columns, theme);
+    let table = Table::new(rows, columns.iter().map(|column| column.width))
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    format!("Song Browser: {}", app.browser.path),
+                    theme.block_title,
+                ))
+                .title(
+                    Title::from(Span::styled(
+                        format!("Total Songs: {}", total_songs),
+                        theme.info,
+                    ))
+                    .alignment(Alignment::Center),
+                )
+                .title(filter_title(filter, app.browser.filetree.len(), theme))
+                .title(selection_title(app))
+                .title(match app.conn.volume_status {
+                    VolumeStatus::Unmuted => Title::from(Span::styled(
+                        format!("Volume: {}%", app.conn.status.volume),
+                        theme.info,
+                    ))
+                    .alignment(Alignment::Right),
+                    VolumeStatus::Muted(_v) => {
+                        Title::from(Span::styled("Muted", theme.muted)).alignment(Alignment::Right)
+                    }
+                })
+                .borders(Borders::ALL),
+        )
+        .highlight_style(theme.highlight)
+        .header(header)
+        .flex(layout::Flex::Legacy);
 
-            if status {
-                row.bold()
-            } else {
-                row
-            }
+    app.browser_state
+        .select(selected_row(filter, app.browser.selected));
+    frame.render_stateful_widget(table, size, &mut app.browser_state);
+}
+
+/// Draws the library, a list of tag values or the tracks of an album
+fn draw_library(frame: &mut Frame, app: &mut App, size: Rect) {
+    let theme = &app.config.theme;
+    let library = &app.library;
+
+    let (rows, widths, header): (Vec<Row>, Vec<Constraint>, Option<Row>) =
+        if library.level == LibraryLevel::Tracks {
+            let columns = &app.config.columns;
+            let styles = column_styles(columns, theme);
+            let rows = library
+                .songs
+                .iter()
+                .enumerate()
+                .map(|(i, song)| {
+                    let row = Row::new(song_cells(song, columns, &styles, "", None, theme));
+                    if app.queue_list.list.iter().any(|s| s.file == song.file) {
+                        row.style(row_style(app, i, theme.in_queue))
+                    } else {
+                        row.style(row_style(app, i, Style::default()))
+                    }
+                })
+                .collect();
+            (
+                rows,
+                columns.iter().map(|column| column.width).collect(),
+                Some(column_header(columns, theme)),
+            )
         } else {
-            let row = Row::new(vec![Cell::from(format!("[{}]", *s))]);
-            row
+            let style = match library.level {
+                LibraryLevel::AlbumArtists => theme.artist,
+                LibraryLevel::Albums => theme.album,
+                _ => theme.title,
+            };
+            let rows = library
+                .items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    Row::new(vec![Cell::from(display_value(Some(item))).style(style)])
+                        .style(row_style(app, i, Style::default()))
+                })
+                .collect();
+            (rows, vec![Constraint::Percentage(100)], None)
+        };
+
+    let filters = [("Genre", &library.genre), ("Date", &library.date)]
+        .iter()
+        .filter_map(|(name, value)| {
+            value
+                .as_ref()
+                .map(|value| format!("{}: {}", name, display_value(Some(value))))
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    let mut block = Block::default()
+        .title(Title::from(Span::styled(
+            format!("Library: {}", library.location()),
+            theme.block_title,
+        )))
+        .borders(Borders::ALL);
+    if !filters.is_empty() {
+        block = block.title(
+            Title::from(Span::styled(format!("[{}]", filters), theme.info))
+                .alignment(Alignment::Center),
+        );
+    }
+    block = block.title(selection_title(app));
+    block = block.title(match app.conn.volume_status {
+        VolumeStatus::Unmuted => Title::from(Span::styled(
+            format!("Volume: {}%", app.conn.status.volume),
+            theme.info,
+        ))
+        .alignment(Alignment::Right),
+        VolumeStatus::Muted(_v) => {
+            Title::from(Span::styled("Muted", theme.muted)).alignment(Alignment::Right)
         }
     });
 
-    let header = ["Artist", "Track", "Title", "Album", "Time"]
-        .into_iter()
-        .map(Cell::from)
-        .collect::<Row>()
-        .bold()
-        .height(1);
-    let table = Table::new(
-        rows,
-        [
-            Constraint::Percentage(34),
-            Constraint::Percentage(3),
-            Constraint::Min(30),
-            Constraint::Percentage(30),
-            Constraint::Percentage(3),
-        ],
-    )
-    .block(
-        Block::default()
-            .title(format!("Song Browser: {}", app.browser.path.clone()).bold())
-            .title(
-                Title::from(format!("Total Songs: {}", total_songs).green())
-                    .alignment(Alignment::Center),
-            )
-            .title(match app.conn.volume_status {
-                VolumeStatus::Unmuted => {
-                    Title::from(format!("Volume: {}%", app.conn.status.volume).green())
-                        .alignment(Alignment::Right)
-                }
-                VolumeStatus::Muted(_v) => {
-                    Title::from("Muted".red()).alignment(Alignment::Right)
-                }
-            })
-            .borders(Borders::ALL),
-    )
-    .highlight_style(
-        Style::default()
-            .add_modifier(Modifier::REVERSED)
-            .fg(Color::Cyan)
-            .bg(Color::Black),
-    )
-    .header(header)
-    .flex(layout::Flex::Legacy);
+    let mut table = Table::new(rows, widths)
+        .block(block)
+        .highlight_style(theme.highlight)
+        .flex(layout::Flex::Legacy);
+    if let Some(header) = header {
+        table = table.header(header);
+    }
 
-    app.browser_state.select(Some(app.browser.selected));
-    frame.render_stateful_widget(table, size, &mut app.browser_state);
+    app.library_state.select(Some(app.library.selected));
+    frame.render_stateful_widget(table, size, &mut app.library_state);
 }
 
-/// draws playing queue
-fn draw_queue(frame: &mut Frame, app: &mut App, size: Rect) {
-    let rows = app.queue_list.list.iter().enumerate().map(|(i, song)| {
-        // metadata
-        let title = song.clone().title.unwrap_or_else(|| song.clone().file);
-        let artist = song.clone().artist.unwrap_or_default().cyan();
-        let album = song
-            .tags
-            .iter()
-            .filter(|(x, _)| x == "Album")
-            .map(|(_, l)| l.clone())
-            .collect::<Vec<String>>()
-            .join("");
-
-        let track = song
-            .tags
-            .iter()
-            .filter(|(x, _)| x == "Track")
-            .map(|(_, l)| l.clone())
-            .collect::<Vec<String>>()
-            .join("");
-
-        let time = App::format_time(song.clone().duration.unwrap_or_else(|| Duration::new(0, 0)));
-
-        let row = Row::new(vec![
-            Cell::from(artist.clone()),
-            Cell::from(track.clone().green()),
-            Cell::from(title.clone()),
-            Cell::from(album.clone().cyan()),
-            Cell::from(time.to_string().magenta()),
-        ]);
-
-        let place = app.conn.current_song.place;
-        if let Some(pos) = place {
-            if i == pos.pos as usize {
-                let row = Row::new(vec![
-                    Cell::from(format!("> {}", artist)),
-                    Cell::from(format!(" {}", track).green()),
-                    Cell::from(format!(" {}", title)),
-                    Cell::from(album.cyan()),
-                    Cell::from(time.to_string().magenta()),
-                ]);
-
-                row
-            } else {
-                row
-            }
+/// Draws the songs found by the last database search
+fn draw_search_results(frame: &mut Frame, app: &mut App, size: Rect) {
+    let theme = &app.config.theme;
+    let columns = &app.config.columns;
+    let styles = column_styles(columns, theme);
+
+    let rows = app.search_results.list.iter().enumerate().map(|(i, song)| {
+        let row = Row::new(song_cells(song, columns, &styles, "", None, theme));
+        if app.queue_list.list.iter().any(|s| s.file == song.file) {
+            row.style(row_style(app, i, theme.in_queue))
         } else {
-            row
+            row.style(row_style(app, i, Style::default()))
         }
     });
 
-    let header = ["Artist", "Track", "Title", "Album", "Time"]
-        .into_iter()
-        .map(Cell::from)
-        .collect::<Row>()
-        .bold()
-        .height(1);
-    let table = Table::new(
-        rows,
-        [
-            Constraint::Percentage(34),
-            Constraint::Percentage(3),
-            Constraint::Min(30),
-            Constraint::Percentage(30),
-            Constraint::Percentage(3),
-        ],
-    )
-    .block(
-        Block::default()
-            .title(Title::from("Play Queue".green().bold()))
-            .title(Title::from(
-                format!("({} items)", app.queue_list.list.len()).bold(),
+    let key = app
+        .config
+        .keys
+        .keys_for(Context::Global, Action::DatabaseSearch);
+    let title = match (app.db_search_query.is_empty(), key) {
+        (true, Some(key)) => format!("Search: press {} to search the database", key),
+        (true, None) => "Search".to_string(),
+        (false, _) => format!("Search: {}", app.db_search_query),
+    };
+    let table = Table::new(rows, columns.iter().map(|column| column.width))
+        .block(
+            Block::default()
+                .title(Title::from(Span::styled(title, theme.block_title)))
+                .title(Title::from(Span::styled(
+                    format!("({} results)", app.search_results.list.len()),
+                    theme.info,
+                )))
+                .title(selection_title(app))
+                .title(match app.conn.volume_status {
+                    VolumeStatus::Unmuted => Title::from(Span::styled(
+                        format!("Volume: {}%", app.conn.status.volume),
+                        theme.info,
+                    ))
+                    .alignment(Alignment::Right),
+                    VolumeStatus::Muted(_v) => {
+                        Title::from(Span::styled("Muted", theme.muted)).alignment(Alignment::Right)
+                    }
+                })
+                .borders(Borders::ALL),
+        )
+        .highlight_style(theme.highlight)
+        .header(column_header(columns, theme))
+        .flex(layout::Flex::Legacy);
+
+    app.search_state.select(Some(app.search_results.index));
+    frame.render_stateful_widget(table, size, &mut app.search_state);
+}
+
+/// draws playing queue
+fn draw_queue(frame: &mut Frame, app: &mut App, size: Rect) {
+    let theme = &app.config.theme;
+    let columns = &app.config.columns;
+    let styles = column_styles(columns, theme);
+    let matcher = search_matcher(app);
+    let filter = app.queue_filter.as_ref();
+    let rows = app
+        .queue_list
+        .list
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| is_visible(filter, *i))
+        .map(|(i, song)| {
+            // The song being played is marked with a '>'
+            let prefix = match app.conn.current_song.place {
+                Some(place) if i == place.pos as usize => "> ",
+                _ => "",
+            };
+            Row::new(song_cells(
+                song,
+                columns,
+                &styles,
+                prefix,
+                matcher.as_ref(),
+                theme,
             ))
-            .title(match app.conn.volume_status {
-                VolumeStatus::Unmuted => {
-                    Title::from(format!("Volume: {}%", app.conn.status.volume).green())
-                        .alignment(Alignment::Right)
-                }
-                VolumeStatus::Muted(_v) => {
-                    Title::from("Muted".red()).alignment(Alignment::Right)
-                }
-            })
-            .borders(Borders::ALL),
-    )
-    .highlight_style(
-        Style::default()
-            .add_modifier(Modifier::REVERSED)
-            .fg(Color::Cyan)
-            .bg(Color::Black),
-    )
-    .header(header)
-    .flex(layout::Flex::Legacy);
+            .style(row_style(app, i, Style::default()))
+        });
+
+    let header = column_header(columns, theme);
+    let table = Table::new(rows, columns.iter().map(|column| column.width))
+        .block(
+            Block::default()
+                .title(Title::from(Span::styled("Play Queue", theme.block_title)))
+                .title(Title::from(Span::styled(
+                    format!("({} items)", app.queue_list.list.len()),
+                    theme.info,
+                )))
+                .title(filter_title(filter, app.queue_list.list.len(), theme))
+                .title(selection_title(app))
+                .title(match app.conn.volume_status {
+                    VolumeStatus::Unmuted => Title::from(Span::styled(
+                        format!("Volume: {}%", app.conn.status.volume),
+                        theme.info,
+                    ))
+                    .alignment(Alignment::Right),
+                    VolumeStatus::Muted(_v) => {
+                        Title::from(Span::styled("Muted", theme.muted)).alignment(Alignment::Right)
+                    }
+                })
+                .borders(Borders::ALL),
+        )
+        .highlight_style(theme.highlight)
+        .header(header)
+        .flex(layout::Flex::Legacy);
 
-    app.queue_state.select(Some(app.queue_list.index));
+    app.queue_state
+        .select(selected_row(filter, app.queue_list.index));
     frame.render_stateful_widget(table, size, &mut app.queue_state);
 }
 
+/// Header row with the column names
+fn column_header<'a>(columns: &'a [Column], theme: &Theme) -> Row<'a> {
+    columns
+        .iter()
+        .map(|column| Cell::from(Line::from(column.header.as_str()).alignment(column.alignment)))
+        .collect::<Row>()
+        .style(theme.header)
+        .height(1)
+}
+
+/// Whether the filter of the list, if any, keeps the row
+fn is_visible(filter: Option<&ListFilter>, index: usize) -> bool {
+    filter.is_none_or(|filter| filter.position(index).is_some())
+}
+
+/// Row of the widget to highlight, counting only the ones left by the filter
+fn selected_row(filter: Option<&ListFilter>, index: usize) -> Option<usize> {
+    match filter {
+        Some(filter) => filter.position(index),
+        None => Some(index),
+    }
+}
+
+/// `Filter: foo (3/10)`, or nothing when the list is not filtered
+fn filter_title<'a>(filter: Option<&ListFilter>, total: usize, theme: &Theme) -> Title<'a> {
+    match filter {
+        Some(filter) => Title::from(Span::styled(
+            format!(
+                " Filter: {} ({}/{}) ",
+                filter.query,
+                filter.visible.len(),
+                total
+            ),
+            theme.info,
+        )),
+        None => Title::from(""),
+    }
+    .alignment(Alignment::Center)
+}
+
+/// Theme style of every column
+fn column_styles(columns: &[Column], theme: &Theme) -> Vec<Style> {
+    columns
+        .iter()
+        .map(|column| theme.style(&column.style).unwrap_or_default())
+        .collect()
+}
+
+/// Cells of a song in the queue or directory browser, `prefix` goes before the first one
+fn song_cells<'a>(
+    song: &Song,
+    columns: &[Column],
+    styles: &[Style],
+    prefix: &str,
+    matcher: Option<&Matcher>,
+    theme: &Theme,
+) -> Vec<Cell<'a>> {
+    columns
+        .iter()
+        .zip(styles)
+        .enumerate()
+        .map(|(i, (column, style))| {
+            let text = if i == 0 {
+                prefix.to_string() + &column.format(song)
+            } else {
+                column.format(song)
+            };
+            let line = highlight(text, matcher, theme.search_match);
+            Cell::from(line.alignment(column.alignment)).style(*style)
+        })
+        .collect()
+}
+
+/// Style of the row, on top of which the selected rows get the selection style
+fn row_style(app: &App, index: usize, style: Style) -> Style {
+    if app.is_selected(index) {
+        style.patch(app.config.theme.selected)
+    } else {
+        style
+    }
+}
+
+/// Number of rows the bulk actions apply to, once some are selected
+fn selection_title<'a>(app: &App) -> Title<'a> {
+    if app.selection.is_empty() {
+        return Title::from("");
+    }
+    let visual = if app.selection.anchor.is_some() {
+        "-- VISUAL -- "
+    } else {
+        ""
+    };
+    Title::from(Span::styled(
+        format!(" {}{} selected ", visual, app.selected_rows().len()),
+        app.config.theme.selected,
+    ))
+    .alignment(Alignment::Center)
+}
+
+/// Text of a row, with the parts matching the search in `style`
+fn highlight<'a>(text: String, matcher: Option<&Matcher>, style: Style) -> Line<'a> {
+    let matches = matcher
+        .map(|matcher| matcher.ranges(&text))
+        .unwrap_or_default();
+    if matches.is_empty() {
+        return Line::from(text);
+    }
+
+    let mut spans = Vec::new();
+    let mut end = 0;
+    for (start, stop) in matches {
+        if start < end {
+            continue;
+        }
+        spans.push(Span::raw(text[end..start].to_string()));
+        spans.push(Span::styled(text[start..stop].to_string(), style));
+        end = stop;
+    }
+    spans.push(Span::raw(text[end..].to_string()));
+    Line::from(spans)
+}
+
+/// Search whose matches are highlighted: the one being typed, or else the last one
+fn search_matcher(app: &App) -> Option<Matcher> {
+    let query = if app.inputmode == InputMode::Editing {
+        &app.search_input
+    } else {
+        &app.last_search
+    };
+    if query.is_empty() {
+        return None;
+    }
+    app.search_matcher(query).ok()
+}
+
 // Draw search bar
 fn draw_search_bar(frame: &mut Frame, app: &mut App, size: Rect) {
     // Make the cursor visible and ask ratatui to put it at the specified coordinates after
@@ -266,18 +523,33 @@ fn draw_search_bar(frame: &mut Frame, app: &mut App, size: Rect) {
         size.y + 1,
     );
 
+    let theme = &app.config.theme;
+    let mode = format!(
+        "{}, {}",
+        app.search_mode.name(),
+        if app.search_case_sensitive {
+            "case-sensitive"
+        } else {
+            "ignore case"
+        }
+    );
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .title(Span::styled("Search Forward: ", theme.block_title))
+        .title(Title::from(Span::styled(mode, theme.info)).alignment(Alignment::Right));
+    if let Err(e) = app.search_matcher(&app.search_input) {
+        block = block.title(Title::from(Span::styled(e, theme.error)).alignment(Alignment::Center));
+    }
     let input = Paragraph::new("/".to_string() + &app.search_input)
         .style(Style::default())
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Search Forward: ".bold().green()),
-        );
+        .block(block);
     frame.render_widget(input, size);
 }
 
 /// Draws Progress Bar
 fn draw_progress_bar(frame: &mut Frame, app: &mut App, size: Rect) {
+    let theme = &app.config.theme;
+
     // Get the current playing song
     let song = app
         .conn
@@ -324,45 +596,98 @@ fn draw_progress_bar(frame: &mut Frame, app: &mut App, size: Rect) {
 
     // Define the title
     let title = Block::default()
-        .title(Title::from(state.red().bold()))
-        .title(Title::from(song.green()))
-        .title(Title::from(duration.cyan().bold()).alignment(Alignment::Right))
+        .title(Title::from(Span::styled(state, theme.state)))
+        .title(Title::from(Span::styled(song, theme.now_playing)))
+        .title(Title::from(Span::styled(duration, theme.elapsed)).alignment(Alignment::Right))
         .title(Title::from(modes_bottom).position(block::Position::Bottom))
         .borders(Borders::ALL);
 
+    // Songs still being added by the background adder
+    let title = if app.adder.is_busy() {
+        let progress = format!("Adding {}/{}", app.adder.done, app.adder.total);
+        title.title(
+            Title::from(Span::styled(progress, theme.info))
+                .position(block::Position::Bottom)
+                .alignment(Alignment::Center),
+        )
+    } else {
+        title
+    };
+
+    // Errors returned by MPD are shown until the next key press
+    let title = match &app.error_message {
+        Some(message) => title.title(
+            Title::from(Span::styled(message.clone(), theme.error))
+                .position(block::Position::Bottom)
+                .alignment(Alignment::Right),
+        ),
+        None => title,
+    };
+
     let progress_bar = LineGauge::default()
         .block(title.borders(Borders::ALL))
-        .gauge_style(
-            Style::default()
-                .fg(Color::Blue)
-                .bg(Color::Black)
-                .add_modifier(Modifier::BOLD),
-        )
+        .gauge_style(theme.progress_bar)
         .line_set(symbols::line::THICK)
         .ratio(app.conn.get_progress_ratio());
 
     frame.render_widget(progress_bar, size);
 }
 
+/// Draws the banner shown in place of the progress bar while MPD is unreachable
+fn draw_connection_banner(frame: &mut Frame, app: &mut App, size: Rect) {
+    let reason = match &app.conn.last_error {
+        Some(err) => format!(" ({})", err),
+        None => String::new(),
+    };
+    let message = match app.conn.conn_state {
+        ConnectionState::Reconnecting { attempt, retry_at } => {
+            let wait = retry_at.saturating_duration_since(Instant::now());
+            format!(
+                "Disconnected from {}{}, retrying in {}s… (attempt {})",
+                app.conn.address,
+                reason,
+                wait.as_secs() + 1,
+                attempt + 1
+            )
+        }
+        _ => format!(
+            "Disconnected from {}{}, retrying…",
+            app.conn.address, reason
+        ),
+    };
+
+    let banner = Paragraph::new(Span::styled(message, app.config.theme.error))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    frame.render_widget(banner, size);
+}
+
 fn draw_playlist_viewer(frame: &mut Frame, app: &mut App, area: Rect) {
     let layouts = Layout::default()
         .direction(Direction::Horizontal)
         .constraints(vec![Constraint::Percentage(40), Constraint::Percentage(60)])
         .split(area);
+    let theme = &app.config.theme;
 
     // Draw list of playlists
-    let title = Block::default().title(Title::from("Playlist".green().bold()));
-    let list = List::new(app.pl_list.list.clone())
+    let filter = app.pl_filter.as_ref();
+    let title = Block::default()
+        .title(Title::from(Span::styled("Playlist", theme.block_title)))
+        .title(filter_title(filter, app.pl_list.list.len(), theme))
+        .title(selection_title(app));
+    let playlists = app
+        .pl_list
+        .list
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| is_visible(filter, *i))
+        .map(|(i, name)| ListItem::new(name.as_str()).style(row_style(app, i, Style::default())));
+    let list = List::new(playlists)
         .block(title.borders(Borders::ALL))
-        .highlight_style(
-            Style::new()
-                .fg(Color::Cyan)
-                .bg(Color::Black)
-                .add_modifier(Modifier::BOLD)
-                .add_modifier(Modifier::REVERSED),
-        )
+        .highlight_style(theme.highlight)
         .repeat_highlight_symbol(true);
-    app.playlists_state.select(Some(app.pl_list.index));
+    app.playlists_state
+        .select(selected_row(filter, app.pl_list.index));
     frame.render_stateful_widget(list, layouts[0], &mut app.playlists_state);
 
     // Playlist viewer
@@ -370,7 +695,7 @@ fn draw_playlist_viewer(frame: &mut Frame, app: &mut App, area: Rect) {
     // Handle if there are no playlists in the mpd database
     if app.pl_list.list.is_empty() {
         let title = Block::default()
-            .title(Title::from("No Playlists Found".red().bold()))
+            .title(Title::from(Span::styled("No Playlists Found", theme.error)))
             .title_alignment(Alignment::Center)
             .borders(Borders::ALL);
         frame.render_widget(Clear, area); //this clears out the background
@@ -378,24 +703,35 @@ fn draw_playlist_viewer(frame: &mut Frame, app: &mut App, area: Rect) {
         return;
     }
 
-    let pl_name = app.pl_list.get_item_at_current_index();
-    let songs = app.conn.conn.playlist(pl_name).unwrap();
+    // The focused songs are the ones the keys act on, the others come from
+    // the cache, empty until they could be fetched
+    let songs: &[Song] = if app.pl_content_focus {
+        &app.pl_songs.list
+    } else {
+        let pl_name = app.pl_list.get_item_at_current_index();
+        app.pl_contents
+            .get(pl_name.as_str())
+            .map_or(&[], Vec::as_slice)
+    };
     let rows = songs.iter().map(|song| {
-        let title = song.clone().title.unwrap_or_default().cyan();
-        let artist = song.clone().artist.unwrap_or_else(|| song.clone().file);
-        let time = App::format_time(song.clone().duration.unwrap_or_else(|| Duration::new(0, 0)));
-
-        let row = Row::new(vec![
-            Cell::from(artist),
-            Cell::from(title),
-            Cell::from(time.to_string().green()),
-        ]);
-        row
+        let title = song.title.as_deref().unwrap_or_default();
+        let artist = song.artist.as_deref().unwrap_or(&song.file);
+        let time = App::format_time(song.duration.unwrap_or_default());
+
+        Row::new(vec![
+            Cell::from(artist).style(theme.artist),
+            Cell::from(title).style(theme.title),
+            Cell::from(time).style(theme.time),
+        ])
     });
 
-    let title = Block::default()
-        .title(format!("Content: ({} items)", songs.len()).bold())
-        .borders(Borders::ALL);
+    let title = format!("Content: ({} items)", songs.len());
+    let title = if app.pl_content_focus {
+        Span::styled(title, theme.block_title)
+    } else {
+        Span::styled(title, theme.info)
+    };
+    let title = Block::default().title(title).borders(Borders::ALL);
     let table = Table::new(
         rows,
         vec![
@@ -405,31 +741,30 @@ fn draw_playlist_viewer(frame: &mut Frame, app: &mut App, area: Rect) {
         ],
     )
     .block(title)
-    .highlight_style(
-        Style::default()
-            .add_modifier(Modifier::REVERSED)
-            .fg(Color::Cyan)
-            .bg(Color::Black),
-    )
+    .highlight_style(theme.highlight)
     .flex(layout::Flex::SpaceBetween);
-    frame.render_widget(table, layouts[1]);
+    app.pl_content_state
+        .select(app.pl_content_focus.then_some(app.pl_songs.index));
+    frame.render_stateful_widget(table, layouts[1], &mut app.pl_content_state);
 }
 
 fn draw_add_to_playlist(frame: &mut Frame, app: &mut App, area: Rect) {
+    let theme = &app.config.theme;
     let area = centered_rect(40, 50, area);
     let mut state = ListState::default();
-    let title = Block::default()
-        .title(Title::from("Add Selected Item to: "))
-        .title(Title::from("<Esc> to Cancel".green().bold()).alignment(Alignment::Right));
+    let mut title = Block::default().title(Title::from("Add Selected Item to: "));
+    if let Some(key) = app.config.keys.keys_for(Context::Popup, Action::Close) {
+        title = title.title(
+            Title::from(Span::styled(
+                format!("{} to Cancel", key),
+                theme.block_title,
+            ))
+            .alignment(Alignment::Right),
+        );
+    }
     let list = List::new(app.append_list.list.clone())
         .block(title.borders(Borders::ALL))
-        .highlight_style(
-            Style::new()
-                .fg(Color::Cyan)
-                .bg(Color::Black)
-                .add_modifier(Modifier::BOLD)
-                .add_modifier(Modifier::REVERSED),
-        )
+        .highlight_style(theme.highlight)
         .repeat_highlight_symbol(true);
 
     state.select(Some(app.append_list.index));
@@ -438,25 +773,46 @@ fn draw_add_to_playlist(frame: &mut Frame, app: &mut App, area: Rect) {
 }
 
 fn draw_rename_playlist(frame: &mut Frame, app: &mut App, area: Rect) {
+    // Paths of files are shown as typed, playlist names after a `/`
+    let (title, prefix) = match app.pl_name_prompt {
+        PlaylistPrompt::Rename => ("Enter New Name: ", "/"),
+        PlaylistPrompt::SaveQueue => ("Save the Queue as: ", "/"),
+        PlaylistPrompt::Duplicate(_) => ("Enter the Copy's Name: ", "/"),
+        PlaylistPrompt::Export(_) => ("Export to File (.m3u8, .pls or .xspf): ", ""),
+        PlaylistPrompt::Import => ("Import from File: ", ""),
+    };
+
     #[allow(clippy::cast_possible_truncation)]
     frame.set_cursor(
         // Draw the cursor at the current position in the input field.
         // This position is can be controlled via the left and right arrow key
-        area.x + app.pl_cursor_pos as u16 + 2,
+        area.x + app.pl_cursor_pos as u16 + prefix.len() as u16 + 1,
         // Move one line down, from the border to the input line
         area.y + 1,
     );
 
-    let input = Paragraph::new("/".to_string() + &app.pl_newname_input)
+    let input = Paragraph::new(prefix.to_string() + &app.pl_newname_input)
         .style(Style::default())
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Enter New Name: ".bold().green()),
+                .title(Span::styled(title, app.config.theme.block_title)),
         );
     frame.render_widget(input, area);
 }
 
+/// Draws the question of the action waiting for confirmation
+fn draw_confirmation(frame: &mut Frame, app: &mut App, area: Rect) {
+    let prompt = app
+        .confirmation
+        .as_ref()
+        .map(|confirmation| confirmation.prompt())
+        .unwrap_or_default();
+    let question = Paragraph::new(Span::styled(prompt, app.config.theme.error))
+        .block(Block::default().borders(Borders::ALL));
+    frame.render_widget(question, area);
+}
+
 fn draw_new_playlist(frame: &mut Frame, app: &mut App, area: Rect) {
     #[allow(clippy::cast_possible_truncation)]
     frame.set_cursor(
@@ -468,15 +824,128 @@ fn draw_new_playlist(frame: &mut Frame, app: &mut App, area: Rect) {
     );
 
     let input = Paragraph::new("/".to_string() + &app.pl_new_pl_input)
+        .style(Style::default())
+        .block(Block::default().borders(Borders::ALL).title(Span::styled(
+            "Enter New Playlist's Name: ",
+            app.config.theme.block_title,
+        )));
+    frame.render_widget(input, area);
+}
+
+fn draw_database_search_bar(frame: &mut Frame, app: &mut App, area: Rect) {
+    #[allow(clippy::cast_possible_truncation)]
+    frame.set_cursor(
+        // Draw the cursor at the current position in the input field.
+        // This position is can be controlled via the left and right arrow key
+        area.x + app.db_search_cursor_pos as u16 + 1,
+        // Move one line down, from the border to the input line
+        area.y + 1,
+    );
+
+    let input = Paragraph::new(app.db_search_input.as_str())
         .style(Style::default())
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Enter New Playlist's Name: ".bold().green()),
+                .title(Span::styled(
+                    "Search Database: ",
+                    app.config.theme.block_title,
+                ))
+                .title(
+                    Title::from(Span::styled(
+                        "artist:foo album:\"bar baz\" date=2001",
+                        app.config.theme.info,
+                    ))
+                    .alignment(Alignment::Right),
+                ),
         );
     frame.render_widget(input, area);
 }
 
+fn draw_filter_bar(frame: &mut Frame, app: &mut App, area: Rect) {
+    #[allow(clippy::cast_possible_truncation)]
+    frame.set_cursor(
+        // Draw the cursor at the current position in the input field.
+        // This position is can be controlled via the left and right arrow key
+        area.x + app.filter_cursor_pos as u16 + 2,
+        // Move one line down, from the border to the input line
+        area.y + 1,
+    );
+
+    let input = Paragraph::new("/".to_string() + &app.filter_input)
+        .style(Style::default())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::styled("Filter: ", app.config.theme.block_title)),
+        );
+    frame.render_widget(input, area);
+}
+
+/// Draws the songs of the playlists missing from the database
+fn draw_playlist_doctor(frame: &mut Frame, app: &mut App, area: Rect) {
+    let theme = &app.config.theme;
+    let area = centered_rect(80, 70, area);
+    let title = if app.dead_entries.list.is_empty() {
+        "No Missing Songs".to_string()
+    } else {
+        format!("Missing Songs ({})", app.dead_entries.list.len())
+    };
+    let block = Block::default()
+        .title(Title::from(Span::styled(title, theme.block_title)))
+        .title(
+            Title::from(Span::styled(doctor_hint(&app.config.keys), theme.info))
+                .alignment(Alignment::Right),
+        )
+        .borders(Borders::ALL);
+
+    let rows = app.dead_entries.list.iter().map(|entry| {
+        let suggestion = match &entry.suggestion {
+            Some(file) => Cell::from(format!("→ {}", file)).style(theme.title),
+            None => Cell::from("no match found").style(theme.muted),
+        };
+        Row::new(vec![
+            Cell::from(entry.playlist.clone()).style(theme.album),
+            Cell::from(entry.song.file.clone()).style(theme.error),
+            suggestion,
+        ])
+    });
+    let table = Table::new(
+        rows,
+        vec![
+            Constraint::Percentage(20),
+            Constraint::Percentage(40),
+            Constraint::Percentage(40),
+        ],
+    )
+    .header(Row::new(vec!["Playlist", "Missing", "Likely moved to"]).style(theme.header))
+    .block(block)
+    .highlight_style(theme.highlight);
+
+    let mut state = TableState::default();
+    state.select((!app.dead_entries.list.is_empty()).then_some(app.dead_entries.index));
+    frame.render_widget(Clear, area); //this clears out the background
+    frame.render_stateful_widget(table, area, &mut state);
+}
+
+/// `f: fix, F: fix all, …` with the keys the actions are bound to
+fn doctor_hint(keys: &Keymap) -> String {
+    [
+        (Action::FixEntry, "fix"),
+        (Action::FixAll, "fix all"),
+        (Action::PruneEntry, "remove"),
+        (Action::PruneAll, "remove all"),
+        (Action::Close, "close"),
+    ]
+    .iter()
+    .filter_map(|(action, label)| {
+        let key = keys.keys_for(Context::Doctor, *action)?;
+        Some(format!("{}: {}", key, label))
+    })
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::vertical([
         Constraint::Percentage((100 - percent_y) / 2),
@@ -1,5 +1,6 @@
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::ffi::OsStr;
+use std::io::{self, Write};
 use std::path::Path;
 
 /// Checks if given program is installed in your system
@@ -12,6 +13,24 @@ pub fn is_installed(ss: &str) -> bool {
     output.status.success()
 }
 
+/// Runs dmenu over the choices and returns the picked one, waiting for it to exit
+pub fn dmenu(choices: &[&str], args: &[&str]) -> io::Result<String> {
+    let mut child = Command::new("dmenu")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        for choice in choices {
+            writeln!(stdin, "{}", choice)?;
+        }
+    }
+
+    let output = child.wait_with_output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
 /// Checks if a file has a given extension
 // https://stackoverflow.com/questions/72392835/check-if-a-file-is-of-a-given-type
 pub trait FileExtension {
@@ -30,3 +49,33 @@ impl<P: AsRef<Path>> FileExtension for P {
     }
 }
 
+
+/// Byte ranges of the text where the query appears, ignoring case
+pub fn find_matches(text: &str, query: &str) -> Vec<(usize, usize)> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    // Lowercase characters along with the position of the one they come from
+    let lowered: Vec<(usize, char)> = text
+        .char_indices()
+        .flat_map(|(i, c)| c.to_lowercase().map(move |l| (i, l)))
+        .collect();
+
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start + query.len() <= lowered.len() {
+        let window = &lowered[start..start + query.len()];
+        if window.iter().map(|(_, c)| c).eq(query.iter()) {
+            let (first, _) = window[0];
+            let (last, _) = window[window.len() - 1];
+            let end = last + text[last..].chars().next().map_or(0, char::len_utf8);
+            matches.push((first, end));
+            start += query.len();
+        } else {
+            start += 1;
+        }
+    }
+    matches
+}